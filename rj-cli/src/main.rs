@@ -1,4 +1,6 @@
-use std::io::{Read, stdin};
+use std::io::{BufRead, Read, stdin, stdout};
+
+use rj::parse::DuplicateKeys;
 
 fn main() -> rj::parse::Result<()> {
     #[rustfmt::skip]
@@ -8,25 +10,237 @@ fn main() -> rj::parse::Result<()> {
             .short('p')
             .long("pretty")
             .action(clap::ArgAction::SetTrue),
+        )
+        .arg(clap::Arg::new("max-depth")
+            .long("max-depth")
+            .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(clap::Arg::new("max-input-size")
+            .long("max-input-size")
+            .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(clap::Arg::new("max-string-len")
+            .long("max-string-len")
+            .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(clap::Arg::new("max-members")
+            .long("max-members")
+            .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(clap::Arg::new("duplicate-keys")
+            .long("duplicate-keys")
+            .value_parser(["reject", "first", "last", "collect"]),
+        )
+        .arg(clap::Arg::new("jsonc")
+            .long("jsonc")
+            .help("Allow comments and trailing commas")
+            .action(clap::ArgAction::SetTrue),
+        )
+        .arg(clap::Arg::new("json5")
+            .long("json5")
+            .help("Read JSON5, and pretty-print it as JSON5")
+            .conflicts_with("jsonc")
+            .action(clap::ArgAction::SetTrue),
+        )
+        .arg(clap::Arg::new("recover")
+            .long("recover")
+            .help("Report every error, and print what could be read")
+            .action(clap::ArgAction::SetTrue),
+        )
+        .arg(clap::Arg::new("repair")
+            .long("repair")
+            .help("Fix common mistakes such as single quotes and missing commas first")
+            .action(clap::ArgAction::SetTrue),
+        )
+        .arg(clap::Arg::new("indent")
+            .long("indent")
+            .help("Spaces to indent pretty output with, or \"tab\"")
+            .value_parser(|s: &str| match s {
+                "tab" => Ok("\t".to_string()),
+                _ => s.parse::<usize>().map(|n| " ".repeat(n)),
+            }),
+        )
+        .arg(clap::Arg::new("max-width")
+            .long("max-width")
+            .help("Keep arrays and objects that fit in this many columns on one line")
+            .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(clap::Arg::new("sort-keys")
+            .long("sort-keys")
+            .help("Sort object members by key")
+            .num_args(0..=1)
+            .default_missing_value("codepoint")
+            .value_parser(["codepoint", "natural"]),
+        )
+        .arg(clap::Arg::new("key-priority")
+            .long("key-priority")
+            .help("Sort object members by key, starting with these comma-separated keys")
+            .value_delimiter(',')
+            .conflicts_with("sort-keys"),
+        )
+        .arg(clap::Arg::new("escape")
+            .long("escape")
+            .help("Which characters to escape in output strings")
+            .value_parser(["minimal", "ascii", "html", "js"]),
+        )
+        .arg(clap::Arg::new("ndjson")
+            .long("ndjson")
+            .help("Read one document per line, and write each back on a line of its own")
+            .conflicts_with_all(["recover", "repair", "pretty"])
+            .action(clap::ArgAction::SetTrue),
         );
 
     let m = cmd.try_get_matches().unwrap_or_else(|e| e.exit());
-    let json: String = m
-        .get_one("json")
-        .map(|x: &String| x.to_string())
-        .unwrap_or_else(|| {
-            let mut buf = Vec::new();
-            let mut handle = stdin().lock();
-            let _ = handle.read_to_end(&mut buf);
-            String::from_utf8_lossy(&buf).to_string()
+    let pretty = m.get_flag("pretty");
+    let mut options = parse_options(&m);
+    if pretty {
+        options = options.for_formatting();
+    }
+    let escape = match m.get_one::<String>("escape").map(String::as_str) {
+        Some("ascii") => rj::Escape::Ascii,
+        Some("html") => rj::Escape::Html,
+        Some("js") => rj::Escape::JavaScript,
+        _ => rj::Escape::Minimal,
+    };
+    let mut format_options = rj::FormatOptions::new().escape(escape);
+    if let Some(indent) = m.get_one::<String>("indent") {
+        format_options = format_options.indent(indent);
+    }
+    if let Some(&width) = m.get_one::<usize>("max-width") {
+        format_options = format_options.layout(rj::Layout::MaxWidth(width));
+    }
+    if let Some(keys) = m.get_many::<String>("key-priority") {
+        let order = rj::KeyOrder::Priority(keys.cloned().collect());
+        format_options = format_options.sort_keys(order);
+    } else if let Some(order) = m.get_one::<String>("sort-keys") {
+        format_options = format_options.sort_keys(match order.as_str() {
+            "natural" => rj::KeyOrder::Natural,
+            _ => rj::KeyOrder::CodePoint,
         });
-    if m.get_flag("pretty") {
-        let formatted = rj::format(&json)?;
+    }
+    if m.get_flag("ndjson") {
+        let json = m.get_one::<String>("json").map(String::as_str);
+        ndjson(json, &options, &format_options);
+        return Ok(());
+    }
+    let mut json = m.get_one::<String>("json").cloned();
+    if m.get_flag("repair") {
+        let input = match json {
+            Some(json) => json,
+            None => read_stdin(),
+        };
+        let (repaired, fixes) = rj::repair(&input);
+        for fix in fixes {
+            eprintln!("fixed: {fix}");
+        }
+        json = Some(repaired);
+    }
+    let json = json.as_deref();
+    let mut errors = Vec::new();
+    let parsed = if m.get_flag("recover") {
+        let parsed;
+        (parsed, errors) = match json {
+            Some(json) => rj::parse_recovering_with(json, &options),
+            None => rj::parse_reader_recovering_with(stdin_decoded(), &options),
+        };
+        parsed
+    } else {
+        match json {
+            Some(json) => rj::parse_with(json, &options)?,
+            None => rj::parse_reader_with(stdin_decoded(), &options)?,
+        }
+    };
+    for e in &errors {
+        eprintln!("error: {e}");
+    }
+    if pretty && m.get_flag("json5") {
+        println!("{}", rj::format_value_json5_with(&parsed, &format_options));
+    } else if pretty {
+        let formatted = rj::format_value_with(&parsed, &format_options);
         println!("{formatted}");
     } else {
-        let parsed = rj::parse(&json)?;
         println!("{:#?}", parsed);
     }
 
+    if !errors.is_empty() {
+        std::process::exit(1);
+    }
     Ok(())
 }
+
+/// Stdin decoded to UTF-8 as it is read, as Windows tools often write
+/// UTF-16.
+fn stdin_decoded() -> impl Read {
+    rj::decode_reader(stdin().lock()).unwrap_or_else(|e| {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    })
+}
+
+/// Reads all of stdin, for the modes that need the whole text.
+fn read_stdin() -> String {
+    std::io::read_to_string(stdin_decoded()).unwrap_or_else(|e| {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    })
+}
+
+/// Reads newline-delimited JSON, reporting bad lines and writing the others
+/// back compactly.
+fn ndjson(json: Option<&str>, options: &rj::ParseOptions, format_options: &rj::FormatOptions) {
+    let input: Box<dyn BufRead> = match json {
+        Some(json) => Box::new(json.as_bytes()),
+        None => Box::new(stdin().lock()),
+    };
+    let reader = rj::ndjson::Reader::new(input).with_options(options.clone());
+    let mut writer = rj::ndjson::Writer::new(stdout().lock()).with_options(format_options.clone());
+    let mut failed = false;
+    for value in reader {
+        let written = match value {
+            Ok(value) => writer.write(&value),
+            Err(e) => {
+                eprintln!("error: {e}");
+                failed = true;
+                continue;
+            }
+        };
+        if let Err(e) = written {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+fn parse_options(m: &clap::ArgMatches) -> rj::ParseOptions {
+    let mut options = if m.get_flag("json5") {
+        rj::ParseOptions::json5()
+    } else if m.get_flag("jsonc") {
+        rj::ParseOptions::jsonc()
+    } else {
+        rj::ParseOptions::new()
+    };
+    if let Some(&n) = m.get_one::<usize>("max-depth") {
+        options = options.max_depth(n);
+    }
+    if let Some(&n) = m.get_one::<usize>("max-input-size") {
+        options = options.max_input_size(n);
+    }
+    if let Some(&n) = m.get_one::<usize>("max-string-len") {
+        options = options.max_string_len(n);
+    }
+    if let Some(&n) = m.get_one::<usize>("max-members") {
+        options = options.max_members(n);
+    }
+    if let Some(policy) = m.get_one::<String>("duplicate-keys") {
+        options = options.duplicate_keys(match policy.as_str() {
+            "reject" => DuplicateKeys::Reject,
+            "first" => DuplicateKeys::FirstWins,
+            "collect" => DuplicateKeys::Collect,
+            _ => DuplicateKeys::LastWins,
+        });
+    }
+    options
+}
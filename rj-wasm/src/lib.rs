@@ -1,3 +1,4 @@
+use rj::parse::DuplicateKeys;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -5,14 +6,182 @@ extern "C" {
     pub fn alert(s: &str);
 }
 
+/// Mirrors [`rj::ParseOptions`] for JavaScript, which cannot use its
+/// consuming builder methods.
 #[wasm_bindgen]
-pub fn format(input: &str) -> Result<String, JsError> {
-    let formatted = rj::format(input)?;
-    Ok(formatted)
+#[derive(Default)]
+pub struct ParseOptions {
+    inner: rj::ParseOptions,
 }
 
 #[wasm_bindgen]
-pub fn parse(input: &str) -> Result<String, JsError> {
-    let parsed = rj::parse(input)?;
+impl ParseOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[wasm_bindgen(setter = maxDepth)]
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.update(|o| o.max_depth(depth));
+    }
+
+    #[wasm_bindgen(setter = maxInputSize)]
+    pub fn set_max_input_size(&mut self, bytes: usize) {
+        self.update(|o| o.max_input_size(bytes));
+    }
+
+    #[wasm_bindgen(setter = maxStringLen)]
+    pub fn set_max_string_len(&mut self, bytes: usize) {
+        self.update(|o| o.max_string_len(bytes));
+    }
+
+    #[wasm_bindgen(setter = maxMembers)]
+    pub fn set_max_members(&mut self, members: usize) {
+        self.update(|o| o.max_members(members));
+    }
+
+    /// Allows comments and trailing commas.
+    #[wasm_bindgen(setter)]
+    pub fn set_jsonc(&mut self, yes: bool) {
+        self.update(|o| o.allow_comments(yes).allow_trailing_commas(yes));
+    }
+
+    /// Reads JSON5, which includes JSONC.
+    #[wasm_bindgen(setter)]
+    pub fn set_json5(&mut self, yes: bool) {
+        self.update(|o| {
+            o.allow_comments(yes)
+                .allow_trailing_commas(yes)
+                .json5_syntax(yes)
+        });
+    }
+
+    /// One of `"reject"`, `"first"`, `"last"` or `"collect"`.
+    #[wasm_bindgen(setter = duplicateKeys)]
+    pub fn set_duplicate_keys(&mut self, policy: &str) -> Result<(), JsError> {
+        let policy = match policy {
+            "reject" => DuplicateKeys::Reject,
+            "first" => DuplicateKeys::FirstWins,
+            "last" => DuplicateKeys::LastWins,
+            "collect" => DuplicateKeys::Collect,
+            _ => {
+                return Err(JsError::new(&format!(
+                    "unknown duplicate key policy: {policy}"
+                )));
+            }
+        };
+        self.update(|o| o.duplicate_keys(policy));
+        Ok(())
+    }
+}
+
+impl ParseOptions {
+    fn update(&mut self, f: impl FnOnce(rj::ParseOptions) -> rj::ParseOptions) {
+        self.inner = f(std::mem::take(&mut self.inner));
+    }
+}
+
+/// Mirrors [`rj::FormatOptions`] for JavaScript.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct FormatOptions {
+    inner: rj::FormatOptions,
+}
+
+#[wasm_bindgen]
+impl FormatOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// What each level of nesting is indented with, such as `"\t"`.
+    #[wasm_bindgen(setter)]
+    pub fn set_indent(&mut self, indent: String) {
+        self.update(|o| o.indent(indent));
+    }
+
+    /// One of `"minimal"`, `"ascii"`, `"html"` or `"js"`.
+    #[wasm_bindgen(setter)]
+    pub fn set_escape(&mut self, policy: &str) -> Result<(), JsError> {
+        let escape = match policy {
+            "minimal" => rj::Escape::Minimal,
+            "ascii" => rj::Escape::Ascii,
+            "html" => rj::Escape::Html,
+            "js" => rj::Escape::JavaScript,
+            _ => {
+                return Err(JsError::new(&format!("unknown escape policy: {policy}")));
+            }
+        };
+        self.update(|o| o.escape(escape));
+        Ok(())
+    }
+
+    /// One of `"codepoint"` or `"natural"`.
+    #[wasm_bindgen(setter = sortKeys)]
+    pub fn set_sort_keys(&mut self, order: &str) -> Result<(), JsError> {
+        let order = match order {
+            "codepoint" => rj::KeyOrder::CodePoint,
+            "natural" => rj::KeyOrder::Natural,
+            _ => return Err(JsError::new(&format!("unknown key order: {order}"))),
+        };
+        self.update(|o| o.sort_keys(order));
+        Ok(())
+    }
+
+    /// Sorts keys with these first, in this order.
+    #[wasm_bindgen(setter = keyPriority)]
+    pub fn set_key_priority(&mut self, keys: Vec<String>) {
+        self.update(|o| o.sort_keys(rj::KeyOrder::Priority(keys)));
+    }
+
+    /// Keeps arrays and objects that fit in this many columns on one line.
+    #[wasm_bindgen(setter = maxWidth)]
+    pub fn set_max_width(&mut self, width: usize) {
+        self.update(|o| o.layout(rj::Layout::MaxWidth(width)));
+    }
+}
+
+impl FormatOptions {
+    fn update(&mut self, f: impl FnOnce(rj::FormatOptions) -> rj::FormatOptions) {
+        self.inner = f(std::mem::take(&mut self.inner));
+    }
+}
+
+/// Pretty-prints `input` as `format_options` say.
+#[wasm_bindgen]
+pub fn format(
+    input: &str,
+    options: &ParseOptions,
+    format_options: &FormatOptions,
+) -> Result<String, JsError> {
+    let options = options.inner.clone().for_formatting();
+    let parsed = rj::parse_with(input, &options)?;
+    Ok(rj::format_value_with(&parsed, &format_options.inner))
+}
+
+/// Pretty-prints as JSON5, whatever syntax `options` accept.
+#[wasm_bindgen(js_name = formatJson5)]
+pub fn format_json5(
+    input: &str,
+    options: &ParseOptions,
+    format_options: &FormatOptions,
+) -> Result<String, JsError> {
+    let options = options.inner.clone().for_formatting();
+    let parsed = rj::parse_with(input, &options)?;
+    Ok(rj::format_value_json5_with(&parsed, &format_options.inner))
+}
+
+#[wasm_bindgen]
+pub fn parse(input: &str, options: &ParseOptions) -> Result<String, JsError> {
+    let parsed = rj::parse_with(input, &options.inner)?;
     Ok(format!("{:#?}", parsed))
 }
+
+/// Every error in `input`, one message each.
+#[wasm_bindgen]
+pub fn diagnostics(input: &str, options: &ParseOptions) -> Vec<String> {
+    let (_, errors) = rj::parse_recovering_with(input, &options.inner);
+    errors.iter().map(|e| e.to_string()).collect()
+}
@@ -0,0 +1,846 @@
+//! A concrete syntax tree that keeps everything the input was written with:
+//! whitespace, comments, commas, escapes and number spellings.
+//!
+//! It is meant for editing files that people also edit by hand. Parts of the
+//! tree that are not changed print back exactly as they were read.
+//!
+//! ```
+//! let input = "{\n  \"version\": \"1.0\", // bump me\n  \"deps\": []\n}";
+//! let mut doc = rj::parse_cst_with(input, &rj::ParseOptions::jsonc()).unwrap();
+//! let root = doc.root_mut().as_object_mut().unwrap();
+//! root.insert("version", &rj::Value::String("1.1".into()));
+//! root.get_mut("deps").unwrap().as_array_mut().unwrap().push(&rj::Value::Null);
+//! assert_eq!(doc.to_string(), "{\n  \"version\": \"1.1\", // bump me\n  \"deps\": [null]\n}");
+//! ```
+
+use std::fmt::{self, Display, Write};
+
+use crate::parse::Result;
+use crate::reader::{Event, Reader};
+use crate::{ParseOptions, Value};
+
+/// A parsed document: its root value and the trivia around it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    leading: String,
+    root: Node,
+    trailing: String,
+}
+
+/// A value as written in the input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    /// A string, number, boolean or null, as written.
+    Scalar(String),
+    Array(Array),
+    Object(Object),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Array(List);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Object(List);
+
+/// The entries of an array or object between its brackets.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct List {
+    entries: Vec<Entry>,
+    trailing_comma: bool,
+    /// Trivia after the last entry, or its trailing comma, up to the closing
+    /// bracket.
+    end: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    /// Trivia after the opening bracket or the comma before the entry.
+    before: String,
+    key: Option<Key>,
+    value: Node,
+    /// Trivia between the value and the comma after it.
+    after: String,
+}
+
+/// The name of an object member, and the trivia around the colon after it.
+#[derive(Debug, Clone, PartialEq)]
+struct Key {
+    raw: String,
+    name: String,
+    before_colon: String,
+    after_colon: String,
+}
+
+impl Document {
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    pub fn root_mut(&mut self) -> &mut Node {
+        &mut self.root
+    }
+}
+
+impl Node {
+    pub fn as_array(&self) -> Option<&Array> {
+        match self {
+            Node::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Array> {
+        match self {
+            Node::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Object> {
+        match self {
+            Node::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut Object> {
+        match self {
+            Node::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    /// The value the node stands for, read as JSON5, which accepts any
+    /// syntax the node may have been parsed with.
+    pub fn to_value(&self) -> Result<Value> {
+        self.to_value_with(&ParseOptions::json5())
+    }
+
+    /// Reads the value with `options`, such as those the document was parsed
+    /// with when they raise a limit or replace lone surrogates.
+    pub fn to_value_with(&self, options: &ParseOptions) -> Result<Value> {
+        crate::parse_with(&self.to_string(), options)
+    }
+}
+
+impl Array {
+    pub fn len(&self) -> usize {
+        self.0.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.entries.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Node> {
+        self.0.entries.get(index).map(|e| &e.value)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Node> {
+        self.0.entries.get_mut(index).map(|e| &mut e.value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Node> {
+        self.0.entries.iter().map(|e| &e.value)
+    }
+
+    /// Replaces the element at `index`, keeping the trivia around it.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: &Value) {
+        self.0.set(index, value);
+    }
+
+    /// Appends `value`, laid out like the elements before it.
+    pub fn push(&mut self, value: &Value) {
+        self.0.insert(self.len(), None, value);
+    }
+
+    /// # Panics
+    ///
+    /// If `index` is greater than the length.
+    pub fn insert(&mut self, index: usize, value: &Value) {
+        assert!(index <= self.len(), "index out of bounds");
+        self.0.insert(index, None, value);
+    }
+
+    /// Removes the element at `index` along with its comma and the trivia
+    /// before it.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Node {
+        self.0.remove(index)
+    }
+}
+
+impl Object {
+    pub fn len(&self) -> usize {
+        self.0.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.entries.is_empty()
+    }
+
+    /// The value of member `name`. If the name occurs more than once, the last
+    /// one wins, as when parsing.
+    pub fn get(&self, name: &str) -> Option<&Node> {
+        self.index(name).map(|i| &self.0.entries[i].value)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Node> {
+        self.index(name).map(|i| &mut self.0.entries[i].value)
+    }
+
+    /// Member names and values, in input order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Node)> {
+        self.0.entries.iter().map(|e| (e.name(), &e.value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.0.entries.iter().map(Entry::name)
+    }
+
+    /// Replaces the value of member `name`, or appends a member laid out like
+    /// the ones before it.
+    pub fn insert(&mut self, name: &str, value: &Value) {
+        if let Some(i) = self.index(name) {
+            self.0.set(i, value);
+            return;
+        }
+        let (before_colon, after_colon) = match self.0.entries.last() {
+            Some(Entry { key: Some(key), .. }) => {
+                (key.before_colon.clone(), key.after_colon.clone())
+            }
+            _ => (String::new(), " ".to_string()),
+        };
+        let key = Key {
+            raw: Value::String(name.to_string()).to_string(),
+            name: name.to_string(),
+            before_colon,
+            after_colon,
+        };
+        self.0.insert(self.len(), Some(key), value);
+    }
+
+    /// Removes member `name` along with its comma and the trivia before it.
+    pub fn remove(&mut self, name: &str) -> Option<Node> {
+        self.index(name).map(|i| self.0.remove(i))
+    }
+
+    fn index(&self, name: &str) -> Option<usize> {
+        self.0.entries.iter().rposition(|e| e.name() == name)
+    }
+}
+
+impl Entry {
+    fn name(&self) -> &str {
+        self.key.as_ref().map_or("", |key| &key.name)
+    }
+}
+
+/// The last line break in `trivia` and the indentation after it.
+fn line_start(trivia: &str) -> Option<&str> {
+    let i = trivia.rfind('\n')?;
+    Some(if trivia[..i].ends_with('\r') {
+        &trivia[i - 1..]
+    } else {
+        &trivia[i..]
+    })
+}
+
+/// A node for `value` as the writers lay it out: compactly, or with each
+/// entry on a line of its own after `line_start` and one more `unit` of
+/// indentation than the line the node starts on.
+fn node(value: &Value, layout: Option<(&str, &str)>) -> Node {
+    let entries: Vec<_> = match value {
+        Value::Array(array) => array.iter().map(|v| (None, v)).collect(),
+        Value::Object(object) => {
+            let after_colon = if layout.is_some() { " " } else { "" };
+            let member = |(name, v)| {
+                let key = Key {
+                    raw: Value::String(String::clone(name)).to_string(),
+                    name: String::clone(name),
+                    before_colon: String::new(),
+                    after_colon: after_colon.to_string(),
+                };
+                (Some(key), v)
+            };
+            object.iter().map(member).collect()
+        }
+        _ => return Node::Scalar(value.to_string()),
+    };
+    let inner = layout.map(|(line_start, unit)| format!("{line_start}{unit}"));
+    let end = match layout {
+        Some((line_start, _)) if !entries.is_empty() => line_start.to_string(),
+        _ => String::new(),
+    };
+    let entries = entries.into_iter().map(|(key, v)| Entry {
+        before: inner.clone().unwrap_or_default(),
+        key,
+        value: node(v, inner.as_deref().zip(layout.map(|(_, unit)| unit))),
+        after: String::new(),
+    });
+    let list = List {
+        entries: entries.collect(),
+        trailing_comma: false,
+        end,
+    };
+    match value {
+        Value::Array(_) => Node::Array(Array(list)),
+        _ => Node::Object(Object(list)),
+    }
+}
+
+impl List {
+    /// If entries are on lines of their own, the line break and indentation
+    /// before each, and the indentation added at each level.
+    fn layout(&self) -> Option<(String, String)> {
+        let close = line_start(&self.end);
+        let inner = match self.entries.iter().find_map(|e| line_start(&e.before)) {
+            Some(inner) => inner.to_string(),
+            None if self.entries.is_empty() => format!("{}  ", close?),
+            None => return None,
+        };
+        let unit = match inner.strip_prefix(close.unwrap_or("\n")) {
+            Some(unit) if !unit.is_empty() => unit.to_string(),
+            _ => "  ".to_string(),
+        };
+        Some((inner, unit))
+    }
+
+    /// A node for `value`, pretty-printed if the entries are on lines of
+    /// their own.
+    fn node(&self, value: &Value) -> Node {
+        let layout = self.layout();
+        node(
+            value,
+            layout.as_ref().map(|(l, u)| (l.as_str(), u.as_str())),
+        )
+    }
+
+    fn set(&mut self, index: usize, value: &Value) {
+        self.entries[index].value = self.node(value);
+    }
+
+    fn insert(&mut self, index: usize, key: Option<Key>, value: &Value) {
+        let layout = self.layout();
+        // What separates an entry from the one before it.
+        let separator = match (&layout, self.entries.len()) {
+            (Some((line_start, _)), _) => line_start.clone(),
+            (None, 0) => String::new(),
+            (None, 1) => " ".to_string(),
+            (None, _) => self.entries[1].before.clone(),
+        };
+        let value = self.node(value);
+        let mut before = separator.clone();
+        if index == 0 {
+            if let Some(first) = self.entries.first_mut() {
+                before = std::mem::replace(&mut first.before, separator);
+            }
+        } else if index == self.entries.len() && !self.trailing_comma {
+            // Comments on the line of the old last entry stay with it.
+            if let Some(newline) = self.end.find(['\r', '\n']) {
+                before = self.end[..newline].to_string() + &before;
+                self.end.drain(..newline);
+            }
+        }
+        let entry = Entry {
+            before,
+            key,
+            value,
+            after: String::new(),
+        };
+        self.entries.insert(index, entry);
+    }
+
+    fn remove(&mut self, index: usize) -> Node {
+        let removed = self.entries.remove(index);
+        let is_last = index == self.entries.len();
+        // The trivia after the removed entry's comma, or up to the bracket.
+        let after = match self.entries.get_mut(index) {
+            Some(next) => &mut next.before,
+            None => &mut self.end,
+        };
+        // What is left of the line before the removed entry, such as a
+        // comment on the entry before it, stays there. The rest of the
+        // removed entry's line, with any comment on it, goes with it.
+        let line_end = removed.before.find(['\r', '\n']);
+        let head = line_end.map_or("", |i| &removed.before[..i]);
+        let tail = match (after.find(['\r', '\n']), line_end) {
+            (Some(i), _) => after[i..].to_string(),
+            (None, Some(i)) => removed.before[i..].to_string(),
+            (None, None) if is_last => after.clone(),
+            (None, None) => removed.before.clone(),
+        };
+        *after = format!("{head}{tail}");
+        if self.entries.is_empty() {
+            self.trailing_comma = false;
+        }
+        removed.value
+    }
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, entry) in self.entries.iter().enumerate() {
+            f.write_str(&entry.before)?;
+            if let Some(key) = &entry.key {
+                write!(f, "{}{}:{}", key.raw, key.before_colon, key.after_colon)?;
+            }
+            write!(f, "{}{}", entry.value, entry.after)?;
+            if i + 1 < self.entries.len() || self.trailing_comma {
+                f.write_char(',')?;
+            }
+        }
+        f.write_str(&self.end)
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.leading, self.root, self.trailing)
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Scalar(text) => f.write_str(text),
+            Node::Array(Array(list)) => {
+                f.write_char('[')?;
+                list.fmt(f)?;
+                f.write_char(']')
+            }
+            Node::Object(Object(list)) => {
+                f.write_char('{')?;
+                list.fmt(f)?;
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+/// A container being read, with where it goes in its parent.
+struct Frame {
+    list: List,
+    object: bool,
+    before: String,
+    key: Option<Key>,
+    /// A member name read in this object, with the trivia before it, waiting
+    /// for its value.
+    pending: Option<(String, Key)>,
+}
+
+pub(crate) fn parse(input: &str, options: &ParseOptions) -> Result<Document> {
+    let mut reader = Reader::new(input).with_options(options.clone());
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut leading = String::new();
+    let mut root = None;
+    // The end of the last token read.
+    let mut pos = 0;
+    while let Some(event) = reader.next_event()? {
+        let end = reader.mark().offset();
+        let start = match event {
+            // After a trailing comma the event starts at the comma.
+            Event::EndArray | Event::EndObject => end - 1,
+            _ => reader.event_start().offset(),
+        };
+        let gap = &input[pos..start];
+        pos = end;
+        let (before, key, value) = match event {
+            Event::StartArray | Event::StartObject => {
+                let (before, key) = place(&mut stack, gap);
+                stack.push(Frame {
+                    list: List::default(),
+                    object: event == Event::StartObject,
+                    before,
+                    key,
+                    pending: None,
+                });
+                continue;
+            }
+            Event::Key(name) => {
+                let frame = stack.last_mut().expect("keys are read in objects");
+                let before = open_entry(&mut frame.list, gap);
+                let raw = key_lexeme(&input[start..]);
+                let key = Key {
+                    raw: raw.to_string(),
+                    name: name.into_owned(),
+                    before_colon: input[start + raw.len()..end - 1].to_string(),
+                    after_colon: String::new(),
+                };
+                frame.pending = Some((before, key));
+                continue;
+            }
+            Event::EndArray | Event::EndObject => {
+                let mut frame = stack.pop().expect("only open containers are closed");
+                close(&mut frame.list, gap);
+                let value = if frame.object {
+                    Node::Object(Object(frame.list))
+                } else {
+                    Node::Array(Array(frame.list))
+                };
+                (frame.before, frame.key, value)
+            }
+            _ => {
+                let (before, key) = place(&mut stack, gap);
+                (before, key, Node::Scalar(input[start..end].to_string()))
+            }
+        };
+        match stack.last_mut() {
+            Some(frame) => frame.list.entries.push(Entry {
+                before,
+                key,
+                value,
+                after: String::new(),
+            }),
+            None => {
+                leading = before;
+                root = Some(value);
+            }
+        }
+    }
+    Ok(Document {
+        leading,
+        root: root.expect("a document has a value"),
+        trailing: input[pos..].to_string(),
+    })
+}
+
+/// Works out the trivia before a value that starts after `gap`, and the
+/// member name it is the value of.
+fn place(stack: &mut [Frame], gap: &str) -> (String, Option<Key>) {
+    let Some(frame) = stack.last_mut() else {
+        return (gap.to_string(), None);
+    };
+    match frame.pending.take() {
+        Some((before, mut key)) => {
+            key.after_colon = gap.to_string();
+            (before, Some(key))
+        }
+        None => (open_entry(&mut frame.list, gap), None),
+    }
+}
+
+/// Splits the trivia before a new entry at the comma after the previous
+/// one, and returns the part that goes with the new entry.
+fn open_entry(list: &mut List, gap: &str) -> String {
+    match (list.entries.last_mut(), split_at_comma(gap)) {
+        (Some(last), Some((after, before))) => {
+            last.after = after.to_string();
+            before.to_string()
+        }
+        _ => gap.to_string(),
+    }
+}
+
+fn close(list: &mut List, gap: &str) {
+    match (list.entries.last_mut(), split_at_comma(gap)) {
+        (Some(last), Some((after, end))) => {
+            last.after = after.to_string();
+            list.trailing_comma = true;
+            list.end = end.to_string();
+        }
+        _ => list.end = gap.to_string(),
+    }
+}
+
+/// Splits trivia at the comma in it, if there is one outside comments.
+fn split_at_comma(trivia: &str) -> Option<(&str, &str)> {
+    let mut i = 0;
+    while let Some(rest) = trivia.get(i..).filter(|rest| !rest.is_empty()) {
+        if rest.starts_with("//") {
+            i += rest.find('\n').unwrap_or(rest.len());
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            i += comment.find("*/").map_or(rest.len(), |end| end + 4);
+        } else if rest.starts_with(',') {
+            return Some((&trivia[..i], &trivia[i + 1..]));
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
+/// The member name at the start of `input`: a quoted string or, in JSON5, an
+/// identifier.
+fn key_lexeme(input: &str) -> &str {
+    let mut chars = input.char_indices();
+    let len = match chars.next() {
+        Some((_, quote @ ('"' | '\''))) => {
+            let mut escaped = false;
+            chars
+                .find(|&(_, c)| {
+                    let end = !escaped && c == quote;
+                    escaped = !escaped && c == '\\';
+                    end
+                })
+                .map_or(input.len(), |(i, _)| i + 1)
+        }
+        _ => input
+            .find(|c: char| {
+                !(c.is_alphanumeric() || matches!(c, '_' | '$' | '\\' | '\u{200C}' | '\u{200D}'))
+            })
+            .unwrap_or(input.len()),
+    };
+    &input[..len]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "// settings\n{\n    \"name\" : \"rj\",\n    \"version\": 1.50e0, // keep\n    \"tags\": [ \"json\",\"\\u0070arser\" ],\n    /* nested */ \"deps\": {\r\n      \"indexmap\": \"2\",\r\n    },\n}\n";
+
+    fn edit(input: &str, f: impl FnOnce(&mut Node)) -> String {
+        let mut doc = parse(input, &ParseOptions::jsonc()).unwrap();
+        f(doc.root_mut());
+        doc.to_string()
+    }
+
+    fn object(node: &mut Node) -> &mut Object {
+        node.as_object_mut().unwrap()
+    }
+
+    fn array(node: &mut Node) -> &mut Array {
+        node.as_array_mut().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let doc = parse(CONFIG, &ParseOptions::jsonc()).unwrap();
+        assert_eq!(doc.to_string(), CONFIG);
+
+        let json5 = "{unquoted: 'single', hex: 0xFF, +1: .5, 'a' /*c*/ :[1,,],}";
+        let doc = parse(json5, &ParseOptions::json5());
+        assert!(doc.is_err());
+        let json5 = "{unquoted: 'single', hex: 0xFF, n: .5, 'a' /*c*/ :[1,],}";
+        let doc = parse(json5, &ParseOptions::json5()).unwrap();
+        assert_eq!(doc.to_string(), json5);
+        let json5 = r"{ π: 1, /* c */ ünï: 2,\u0061\u200Cb: 3 }";
+        let doc = parse(json5, &ParseOptions::json5()).unwrap();
+        assert_eq!(doc.to_string(), json5);
+        let keys: Vec<_> = doc.root().as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["π", "ünï", "a\u{200C}b"]);
+
+        for json in [
+            "1",
+            " \"a\" ",
+            "[]",
+            "{ }",
+            "[[], {}]",
+            "\t{\"a\":{\"b\":[null]}}\n",
+        ] {
+            assert_eq!(parse(json, &ParseOptions::new()).unwrap().to_string(), json);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse("[1 2]", &ParseOptions::new()).is_err());
+        assert!(parse("[1,]", &ParseOptions::new()).is_err());
+    }
+
+    #[test]
+    fn navigation() {
+        let doc = parse(CONFIG, &ParseOptions::jsonc()).unwrap();
+        let root = doc.root().as_object().unwrap();
+        assert_eq!(root.len(), 4);
+        assert_eq!(
+            root.keys().collect::<Vec<_>>(),
+            ["name", "version", "tags", "deps"]
+        );
+        assert_eq!(root.get("version"), Some(&Node::Scalar("1.50e0".into())));
+        let tags = root.get("tags").unwrap().as_array().unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(
+            tags.get(1).unwrap().to_value().unwrap(),
+            Value::String("parser".into())
+        );
+        assert!(root.get("missing").is_none());
+        assert!(root.get("name").unwrap().as_array().is_none());
+        let deps = root.get("deps").unwrap();
+        assert_eq!(
+            deps.to_value().unwrap(),
+            Value::from(r#"{"indexmap": "2"}"#)
+        );
+    }
+
+    #[test]
+    fn replace_values() {
+        let out = edit(CONFIG, |root| {
+            object(root).insert("version", &Value::from("2"));
+            array(object(root).get_mut("tags").unwrap()).set(0, &Value::Boolean(true));
+        });
+        assert_eq!(
+            out,
+            CONFIG.replace("1.50e0", "2").replace("\"json\",", "true,")
+        );
+
+        let out = edit("{\n  \"a\": 1,\n  \"b\": 2\n}", |root| {
+            object(root).insert("a", &Value::from(r#"{"x": [1], "y": {}}"#));
+        });
+        assert_eq!(
+            out,
+            "{\n  \"a\": {\n    \"x\": [\n      1\n    ],\n    \"y\": {}\n  },\n  \"b\": 2\n}"
+        );
+    }
+
+    #[test]
+    fn insert_members() {
+        let out = edit(CONFIG, |root| {
+            object(object(root).get_mut("deps").unwrap()).insert("clap", &Value::from("4"));
+            object(root).insert("edition", &Value::from("[2024]"));
+        });
+        assert_eq!(
+            out,
+            CONFIG
+                .replace("\"2\",\r\n", "\"2\",\r\n      \"clap\": 4,\r\n")
+                .replace(
+                    "    },\n}",
+                    "    },\n    \"edition\": [\n        2024\n    ],\n}"
+                )
+        );
+
+        let out = edit("{\n\t\"a\": 1 // one\n}", |root| {
+            object(root).insert("b", &Value::Null)
+        });
+        assert_eq!(out, "{\n\t\"a\": 1, // one\n\t\"b\": null\n}");
+
+        let out = edit("{ \"a\":1 }", |root| {
+            object(root).insert("b", &Value::from("[1, 2]"))
+        });
+        assert_eq!(out, "{ \"a\":1, \"b\":[1,2] }");
+
+        assert_eq!(
+            edit("{}", |root| object(root).insert("a", &Value::Null)),
+            "{\"a\": null}"
+        );
+        let out = edit("{\n}", |root| object(root).insert("a", &Value::Null));
+        assert_eq!(out, "{\n  \"a\": null\n}");
+    }
+
+    #[test]
+    fn insert_elements() {
+        let out = edit("[1,2]", |root| {
+            array(root).push(&Value::from("3"));
+            array(root).insert(0, &Value::from("0"));
+        });
+        assert_eq!(out, "[0,1,2,3]");
+
+        let out = edit("[1]", |root| array(root).push(&Value::from("2")));
+        assert_eq!(out, "[1, 2]");
+
+        let out = edit("[\n  // first\n  1\n]", |root| {
+            array(root).insert(0, &Value::from("0"));
+            array(root).insert(2, &Value::from("2"));
+        });
+        assert_eq!(out, "[\n  // first\n  0,\n  1,\n  2\n]");
+    }
+
+    #[test]
+    fn remove_entries() {
+        let out = edit(CONFIG, |root| {
+            assert!(object(root).remove("name").is_some());
+            assert!(object(root).remove("missing").is_none());
+            let removed = object(root).remove("deps").unwrap();
+            assert_eq!(
+                removed.to_value().unwrap(),
+                Value::from(r#"{"indexmap": "2"}"#)
+            );
+        });
+        assert_eq!(
+            out,
+            "// settings\n{\n    \"version\": 1.50e0, // keep\n    \"tags\": [ \"json\",\"\\u0070arser\" ],\n}\n"
+        );
+
+        let out = edit("[1, 2, 3]", |root| {
+            array(root).remove(0);
+            array(root).remove(1);
+        });
+        assert_eq!(out, "[2]");
+        assert_eq!(edit("[1, 2]", |root| drop(array(root).remove(1))), "[1]");
+        assert_eq!(edit("[1,]", |root| drop(array(root).remove(0))), "[]");
+        assert_eq!(
+            edit("[\n  1\n]", |root| drop(array(root).remove(0))),
+            "[\n]"
+        );
+
+        let commented = "{ // top\n  \"a\": 1, // one\n  \"b\": 2, // two\n  \"c\": 3 // three\n}";
+        let remove = |name| edit(commented, |root| drop(object(root).remove(name)));
+        assert_eq!(
+            remove("a"),
+            "{ // top\n  \"b\": 2, // two\n  \"c\": 3 // three\n}"
+        );
+        assert_eq!(
+            remove("b"),
+            "{ // top\n  \"a\": 1, // one\n  \"c\": 3 // three\n}"
+        );
+        assert_eq!(
+            remove("c"),
+            "{ // top\n  \"a\": 1, // one\n  \"b\": 2 // two\n}"
+        );
+        let out = edit("[\r\n  1, // one\r\n  2, // two\r\n]", |root| {
+            drop(array(root).remove(1))
+        });
+        assert_eq!(out, "[\r\n  1, // one\r\n]");
+        assert_eq!(
+            edit("[1, // one\n 2]", |root| drop(array(root).remove(1))),
+            "[1 // one\n ]"
+        );
+    }
+
+    #[test]
+    fn to_value_with_options() {
+        let options = ParseOptions::new().lone_surrogates(crate::parse::LoneSurrogates::Replace);
+        let doc = parse(r#"["\uD800"]"#, &options).unwrap();
+        assert!(doc.root().to_value().is_err());
+        assert_eq!(
+            doc.root().to_value_with(&options).unwrap(),
+            Value::from(r#"["\uFFFD"]"#)
+        );
+
+        let deep = format!("{}{}", "[".repeat(200), "]".repeat(200));
+        let options = ParseOptions::new().max_depth(300);
+        let doc = parse(&deep, &options).unwrap();
+        assert!(doc.root().to_value().is_err());
+        assert!(doc.root().to_value_with(&options).is_ok());
+    }
+
+    #[test]
+    fn insert_deep_values() {
+        let deep = format!("{}{}", "[".repeat(200), "]".repeat(200));
+        let value = crate::parse_with(&deep, &ParseOptions::new().max_depth(300)).unwrap();
+        assert_eq!(
+            edit("[]", |root| array(root).push(&value)),
+            format!("[{deep}]")
+        );
+        let out = edit("{\n  \"a\": 1\n}", |root| object(root).insert("b", &value));
+        assert!(out.starts_with("{\n  \"a\": 1,\n  \"b\": [\n    [\n      [\n"));
+        assert!(out.ends_with("\n    ]\n  ]\n}"));
+
+        // Nodes print as the pretty-printer writes the value.
+        for json in [r#"{"a":[1,{},[]],"b":{"c":"\n"},"d":[]}"#, "[]", "2.50"] {
+            let value = Value::from(json);
+            let options = crate::FormatOptions::new().indent("\t");
+            let pretty = crate::generate::format(&value, &options).replace('\n', "\r\n  ");
+            assert_eq!(node(&value, Some(("\r\n  ", "\t"))).to_string(), pretty);
+            assert_eq!(node(&value, None).to_string(), value.to_string());
+        }
+    }
+
+    #[test]
+    fn split_trivia() {
+        assert_eq!(split_at_comma(" , "), Some((" ", " ")));
+        assert_eq!(
+            split_at_comma(" /* , */ ,// ,\n"),
+            Some((" /* , */ ", "// ,\n"))
+        );
+        assert_eq!(split_at_comma(" // ,\n "), None);
+        assert_eq!(key_lexeme(r#""a\"b": 1"#), r#""a\"b""#);
+        assert_eq!(key_lexeme("ab_$1 : 1"), "ab_$1");
+    }
+}
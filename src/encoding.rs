@@ -0,0 +1,421 @@
+//! Detection and decoding of the Unicode encodings JSON text may come in.
+//!
+//! RFC 8259 requires UTF-8 between systems that are not part of a closed
+//! ecosystem, but older specifications allowed UTF-16 and UTF-32 too, and
+//! plenty of tools still write them.
+
+use std::borrow::Cow;
+use std::io::{self, Read};
+
+use crate::parse::{EXCERPT_LEN, Error, Position, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf32Le => "UTF-32LE",
+            Encoding::Utf32Be => "UTF-32BE",
+        }
+    }
+
+    fn unit_len(self) -> usize {
+        match self {
+            Encoding::Utf8 => 1,
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+            Encoding::Utf32Le | Encoding::Utf32Be => 4,
+        }
+    }
+}
+
+/// Text decoded from bytes, with what is needed to map offsets back.
+#[derive(Debug)]
+pub(crate) struct Decoded<'a> {
+    pub(crate) text: Cow<'a, str>,
+    encoding: Encoding,
+    /// Length of the byte order mark that was stripped.
+    bom: usize,
+}
+
+impl Decoded<'_> {
+    /// Converts an offset in `text` to one in the input bytes.
+    pub(crate) fn input_offset(&self, offset: usize) -> usize {
+        let Some(before) = self.text.get(..offset) else {
+            return self.bom + offset;
+        };
+        let units = match self.encoding {
+            Encoding::Utf8 => before.len(),
+            Encoding::Utf16Le | Encoding::Utf16Be => before.encode_utf16().count(),
+            Encoding::Utf32Le | Encoding::Utf32Be => before.chars().count(),
+        };
+        self.bom + units * self.encoding.unit_len()
+    }
+}
+
+/// Works out the encoding from a byte order mark, or else from where the
+/// zero bytes are in the first few bytes: JSON text starts with an ASCII
+/// character. Returns the encoding and the length of the byte order mark.
+fn detect(input: &[u8]) -> (Encoding, usize) {
+    match input {
+        [0xEF, 0xBB, 0xBF, ..] => (Encoding::Utf8, 3),
+        [0xFF, 0xFE, 0, 0, ..] => (Encoding::Utf32Le, 4),
+        [0, 0, 0xFE, 0xFF, ..] => (Encoding::Utf32Be, 4),
+        [0xFF, 0xFE, ..] => (Encoding::Utf16Le, 2),
+        [0xFE, 0xFF, ..] => (Encoding::Utf16Be, 2),
+        [0, 0, 0, _, ..] => (Encoding::Utf32Be, 0),
+        [_, 0, 0, 0, ..] => (Encoding::Utf32Le, 0),
+        [0, _, ..] => (Encoding::Utf16Be, 0),
+        [_, 0, ..] => (Encoding::Utf16Le, 0),
+        _ => (Encoding::Utf8, 0),
+    }
+}
+
+pub(crate) fn decode(input: &[u8]) -> Result<Decoded<'_>> {
+    let (encoding, bom) = detect(input);
+    let bytes = &input[bom..];
+    let text = match encoding {
+        Encoding::Utf8 => match std::str::from_utf8(bytes) {
+            Ok(text) => Cow::Borrowed(text),
+            Err(e) => {
+                let valid = e.valid_up_to();
+                // The prefix is valid, so this cannot fail.
+                let before = std::str::from_utf8(&bytes[..valid]).unwrap_or_default();
+                let rest = String::from_utf8_lossy(&bytes[valid..]);
+                return Err(Error::InvalidUtf8(position(before, bom + valid, &rest)));
+            }
+        },
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let units = bytes.chunks(2).map(|unit| match (unit, encoding) {
+                ([lo, hi], Encoding::Utf16Le) => Some(u16::from_le_bytes([*lo, *hi])),
+                ([hi, lo], _) => Some(u16::from_be_bytes([*hi, *lo])),
+                _ => None,
+            });
+            let mut text = String::with_capacity(bytes.len() / 2);
+            let mut offset = bom;
+            let chars = char::decode_utf16(units.clone().map_while(|unit| unit));
+            for c in chars {
+                match c {
+                    Ok(c) => {
+                        text.push(c);
+                        offset += c.len_utf16() * 2;
+                    }
+                    Err(_) => break,
+                }
+            }
+            if offset < input.len() {
+                let rest = units.skip((offset - bom) / 2).map_while(|unit| unit);
+                let rest: String = char::decode_utf16(rest)
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect();
+                return Err(invalid(encoding, &text, offset, &rest));
+            }
+            Cow::Owned(text)
+        }
+        Encoding::Utf32Le | Encoding::Utf32Be => {
+            let mut text = String::with_capacity(bytes.len() / 4);
+            for (i, unit) in bytes.chunks(4).enumerate() {
+                let c = match (unit, encoding) {
+                    ([a, b, c, d], Encoding::Utf32Le) => {
+                        char::from_u32(u32::from_le_bytes([*a, *b, *c, *d]))
+                    }
+                    ([a, b, c, d], _) => char::from_u32(u32::from_be_bytes([*a, *b, *c, *d])),
+                    _ => None,
+                };
+                match c {
+                    Some(c) => text.push(c),
+                    None => return Err(invalid(encoding, &text, bom + i * 4, "")),
+                }
+            }
+            Cow::Owned(text)
+        }
+    };
+    Ok(Decoded {
+        text,
+        encoding,
+        bom,
+    })
+}
+
+fn invalid(encoding: Encoding, before: &str, offset: usize, rest: &str) -> Error {
+    Error::InvalidEncoding(encoding.name().to_string(), position(before, offset, rest))
+}
+
+/// Position of the input byte at `offset`, where `before` is the decoded text
+/// that precedes it and `rest` the text that follows.
+fn position(before: &str, offset: usize, rest: &str) -> Position {
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = &before[line_start..];
+    Position {
+        offset,
+        line: before.matches('\n').count() + 1,
+        column: line.len() + 1,
+        column_utf16: line.encode_utf16().count() + 1,
+        excerpt: rest.chars().take(EXCERPT_LEN).collect(),
+    }
+}
+
+/// Decodes input in any of the encodings to UTF-8 as it is read, without
+/// the byte order mark. UTF-8 passes through unchecked.
+pub(crate) struct DecodeReader<R> {
+    reader: R,
+    encoding: Encoding,
+    /// Bytes read but not yet decoded.
+    raw: Vec<u8>,
+    /// Decoded bytes, of which those before `pos` have been returned.
+    out: Vec<u8>,
+    pos: usize,
+}
+
+/// Reads the first few bytes of `reader` to work out its encoding.
+pub(crate) fn decode_reader<R: Read>(mut reader: R) -> io::Result<DecodeReader<R>> {
+    let mut raw = Vec::new();
+    (&mut reader).take(4).read_to_end(&mut raw)?;
+    let (encoding, bom) = detect(&raw);
+    raw.drain(..bom);
+    Ok(DecodeReader {
+        reader,
+        encoding,
+        raw,
+        out: Vec::new(),
+        pos: 0,
+    })
+}
+
+impl<R: Read> DecodeReader<R> {
+    /// Moves the complete characters in `raw` to `out`.
+    fn decode(&mut self, eof: bool) -> io::Result<()> {
+        let raw = &self.raw;
+        let mut i = 0;
+        let mut buf = [0; 4];
+        while let Some(unit) = raw.get(i..i + self.encoding.unit_len()) {
+            let (c, len) = match (self.encoding, unit) {
+                (Encoding::Utf8, _) => {
+                    self.out.extend_from_slice(raw);
+                    i = raw.len();
+                    break;
+                }
+                (Encoding::Utf16Le | Encoding::Utf16Be, &[a, b]) => {
+                    let unit16 = |a, b| match self.encoding {
+                        Encoding::Utf16Le => u16::from_le_bytes([a, b]),
+                        _ => u16::from_be_bytes([a, b]),
+                    };
+                    let high = unit16(a, b);
+                    let units = match raw.get(i + 2..i + 4) {
+                        _ if !(0xD800..0xDC00).contains(&high) => vec![high],
+                        Some(&[c, d]) => vec![high, unit16(c, d)],
+                        // The low surrogate is still to come.
+                        _ if !eof => break,
+                        _ => vec![high],
+                    };
+                    let c = char::decode_utf16(units.iter().copied()).next();
+                    match c {
+                        Some(Ok(c)) => (Some(c), c.len_utf16() * 2),
+                        _ => (None, 0),
+                    }
+                }
+                (_, &[a, b, c, d]) => {
+                    let unit = match self.encoding {
+                        Encoding::Utf32Le => u32::from_le_bytes([a, b, c, d]),
+                        _ => u32::from_be_bytes([a, b, c, d]),
+                    };
+                    (char::from_u32(unit), 4)
+                }
+                _ => unreachable!("units are as long as the encoding says"),
+            };
+            let Some(c) = c else {
+                return Err(self.invalid());
+            };
+            self.out
+                .extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            i += len;
+        }
+        self.raw.drain(..i);
+        if eof && !self.raw.is_empty() {
+            return Err(self.invalid());
+        }
+        Ok(())
+    }
+
+    fn invalid(&self) -> io::Error {
+        let message = format!("Invalid {} sequence", self.encoding.name());
+        io::Error::new(io::ErrorKind::InvalidData, message)
+    }
+}
+
+impl<R: Read> Read for DecodeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.out.len() {
+            if self.encoding == Encoding::Utf8 && self.raw.is_empty() {
+                return self.reader.read(buf);
+            }
+            let mut eof = false;
+            if self.encoding != Encoding::Utf8 {
+                let mut chunk = [0; 8192];
+                let n = self.reader.read(&mut chunk)?;
+                self.raw.extend_from_slice(&chunk[..n]);
+                eof = n == 0;
+            }
+            self.out.clear();
+            self.pos = 0;
+            self.decode(eof)?;
+            if eof && self.out.is_empty() {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.out.len() - self.pos);
+        buf[..n].copy_from_slice(&self.out[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn utf16be(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    fn utf32le(s: &str) -> Vec<u8> {
+        s.chars().flat_map(|c| u32::from(c).to_le_bytes()).collect()
+    }
+
+    fn utf32be(s: &str) -> Vec<u8> {
+        s.chars().flat_map(|c| u32::from(c).to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn detect_encodings() {
+        let json = "{\"a\": \"\u{e9}\u{1F600}\"}";
+        let cases = [
+            (json.as_bytes().to_vec(), Encoding::Utf8, 0),
+            (
+                [&[0xEF, 0xBB, 0xBF], json.as_bytes()].concat(),
+                Encoding::Utf8,
+                3,
+            ),
+            (utf16le(json), Encoding::Utf16Le, 0),
+            (utf16be(json), Encoding::Utf16Be, 0),
+            (utf16le("\u{FEFF}1"), Encoding::Utf16Le, 2),
+            (utf16be("\u{FEFF}1"), Encoding::Utf16Be, 2),
+            (utf16le("1"), Encoding::Utf16Le, 0),
+            (utf16be("1"), Encoding::Utf16Be, 0),
+            (utf32le(json), Encoding::Utf32Le, 0),
+            (utf32be(json), Encoding::Utf32Be, 0),
+            (utf32le("\u{FEFF}[]"), Encoding::Utf32Le, 4),
+            (utf32be("\u{FEFF}[]"), Encoding::Utf32Be, 4),
+            (b"1".to_vec(), Encoding::Utf8, 0),
+            (vec![], Encoding::Utf8, 0),
+        ];
+        for (bytes, encoding, bom) in cases {
+            assert_eq!(detect(&bytes), (encoding, bom), "{bytes:?}");
+            let decoded = decode(&bytes).unwrap();
+            assert_eq!(decoded.text.trim_start_matches('\u{FEFF}'), decoded.text);
+        }
+        assert_eq!(decode(&utf16be(json)).unwrap().text, json);
+        assert_eq!(decode(&utf32le(json)).unwrap().text, json);
+        assert!(matches!(
+            decode(json.as_bytes()).unwrap().text,
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn input_offsets() {
+        let json = "[\"\u{e9}\u{1F600}\", x]";
+        let x = json.find('x').unwrap();
+        let utf8 = [&[0xEF, 0xBB, 0xBF], json.as_bytes()].concat();
+        assert_eq!(decode(&utf8).unwrap().input_offset(x), 3 + x);
+        assert_eq!(decode(&utf16le(json)).unwrap().input_offset(x), 2 * 8);
+        assert_eq!(decode(&utf16le("\u{FEFF}[x]")).unwrap().input_offset(1), 4);
+        assert_eq!(decode(&utf32be(json)).unwrap().input_offset(x), 4 * 7);
+    }
+
+    /// Returns one byte per read, to split characters between reads.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(1);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    fn read_decoded(bytes: &[u8]) -> io::Result<String> {
+        io::read_to_string(decode_reader(Trickle(bytes))?)
+    }
+
+    #[test]
+    fn decode_as_read() {
+        let json = "{\"a\": \"\u{e9}\u{1F600}\"}";
+        for bytes in [
+            json.as_bytes().to_vec(),
+            [&[0xEF, 0xBB, 0xBF], json.as_bytes()].concat(),
+            utf16le(json),
+            utf16be(&format!("\u{FEFF}{json}")),
+            utf32le(&format!("\u{FEFF}{json}")),
+            utf32be(json),
+        ] {
+            assert_eq!(read_decoded(&bytes).unwrap(), json, "{bytes:?}");
+        }
+        assert_eq!(read_decoded(b"1").unwrap(), "1");
+        assert_eq!(read_decoded(b"").unwrap(), "");
+
+        let mut bytes = utf16le("[\"");
+        bytes.extend([0x00, 0xD8, b'"', 0]);
+        let err = read_decoded(&bytes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "Invalid UTF-16LE sequence");
+        let mut bytes = utf16be("[1]");
+        bytes.push(0);
+        assert!(read_decoded(&bytes).is_err());
+        let mut bytes = utf32le("[1]");
+        bytes.extend([0, 0, 0x11, 0]);
+        assert!(read_decoded(&bytes).is_err());
+    }
+
+    #[test]
+    fn invalid_input() {
+        let err = decode(b"[\"a\",\n \"\xC3\x28\"]").unwrap_err();
+        assert!(matches!(err, Error::InvalidUtf8(_)));
+        assert_eq!(err.position().offset, 8);
+        assert_eq!(err.position().line, 2);
+        assert_eq!(err.position().column, 3);
+
+        let mut bytes = utf16le("[\"a\", \"");
+        bytes.extend([0x00, 0xD8, b'"', 0, b']', 0]);
+        let err = decode(&bytes).unwrap_err();
+        assert!(matches!(err, Error::InvalidEncoding(ref e, _) if e == "UTF-16LE"));
+        assert_eq!(err.position().offset, 14);
+        assert_eq!(err.position().column, 8);
+        assert_eq!(err.position().excerpt, "\u{FFFD}\"]");
+
+        let mut bytes = utf16be("[1]");
+        bytes.push(0);
+        let err = decode(&bytes).unwrap_err();
+        assert_eq!(err.position().offset, 6);
+
+        let mut bytes = utf32le("[1]");
+        bytes.extend([0, 0, 0x11, 0]);
+        let err = decode(&bytes).unwrap_err();
+        assert!(matches!(err, Error::InvalidEncoding(ref e, _) if e == "UTF-32LE"));
+        assert_eq!(err.position().offset, 12);
+    }
+}
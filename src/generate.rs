@@ -1,84 +1,507 @@
-use crate::Value;
+use indexmap::IndexMap;
+
+use crate::{KeyOrder, Number, Value};
+
+/// Which characters the writers escape in strings, on top of the quotes,
+/// backslashes and control characters that JSON requires.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Escape {
+    #[default]
+    Minimal,
+    /// Everything outside ASCII, as `\uXXXX` or a surrogate pair of them.
+    Ascii,
+    /// `<`, `>`, `&` and `'`, so the output can be put in a `<script>`
+    /// element, as well as U+2028 and U+2029.
+    Html,
+    /// U+2028 and U+2029, which end lines in JavaScript before ES2019.
+    JavaScript,
+}
 
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Value::String(x) => write!(f, "\"{x}\""),
-            Value::Number(x) => write!(f, "{x}"),
-            Value::Boolean(x) => write!(f, "{x}"),
-            Value::Null => write!(f, "null"),
-            Value::Object(obj) => {
-                let mut buf = String::new();
-                buf.push('{');
-                let mut members: Vec<String> = Vec::new();
-                for (k, v) in obj.iter() {
-                    members.push(format!("{}:{}", &Value::String(k.to_string()), v));
-                }
-                buf.push_str(&members.join(","));
-                buf.push('}');
-                write!(f, "{buf}")
+        f.write_str(&stringify(self, &FormatOptions::new()))
+    }
+}
+
+/// Writes `value` compactly. Of `options`, only the escaping and key order
+/// apply. Infinite and NaN numbers, which JSON cannot express, are written
+/// as `null`, as JavaScript's `JSON.stringify` does.
+pub(crate) fn stringify(value: &Value, options: &FormatOptions) -> String {
+    let escape = options.escape;
+    match value {
+        Value::String(x) => quote(x, escape),
+        Value::Number(Number::Float(x)) if !x.is_finite() => "null".to_string(),
+        Value::Object(obj) => {
+            let mut buf = String::new();
+            buf.push('{');
+            let mut members: Vec<String> = Vec::new();
+            for (k, v) in members_of(obj, options) {
+                members.push(format!("{}:{}", quote(k, escape), stringify(v, options)));
+            }
+            buf.push_str(&members.join(","));
+            buf.push('}');
+            buf
+        }
+        Value::Array(arr) => {
+            let mut buf = String::new();
+            buf.push('[');
+            let mut elements: Vec<String> = Vec::new();
+            for v in arr {
+                elements.push(stringify(v, options));
             }
-            Value::Array(arr) => {
-                let mut buf = String::new();
-                buf.push('[');
-                let mut elements: Vec<String> = Vec::new();
-                for v in arr {
-                    elements.push(v.to_string());
+            buf.push_str(&elements.join(","));
+            buf.push(']');
+            buf
+        }
+        Value::Number(x) => x.to_string(),
+        Value::Boolean(x) => x.to_string(),
+        Value::Null => "null".to_string(),
+    }
+}
+
+/// Quotes `s` as a JSON string, escaping quotes, backslashes and control
+/// characters.
+pub(crate) fn quote(s: &str, escape: Escape) -> String {
+    let mut buf = String::with_capacity(s.len() + 2);
+    buf.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            '\x08' => buf.push_str("\\b"),
+            '\x0C' => buf.push_str("\\f"),
+            c if c < ' ' => buf.push_str(&format!("\\u{:04x}", c as u32)),
+            '<' | '>' | '&' | '\'' if escape == Escape::Html => {
+                buf.push_str(&format!("\\u{:04x}", c as u32));
+            }
+            '\u{2028}' | '\u{2029}' if escape != Escape::Minimal => {
+                buf.push_str(&format!("\\u{:04x}", c as u32));
+            }
+            c if !c.is_ascii() && escape == Escape::Ascii => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    buf.push_str(&format!("\\u{unit:04x}"));
                 }
-                buf.push_str(&elements.join(","));
-                buf.push(']');
-                write!(f, "{buf}")
             }
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+    buf
+}
+
+/// Line break written by the pretty-printers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Newline {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl Newline {
+    fn as_str(self) -> &'static str {
+        match self {
+            Newline::Lf => "\n",
+            Newline::CrLf => "\r\n",
+        }
+    }
+}
+
+/// How the pretty-printers write arrays and objects without entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmptyContainers {
+    /// `{}` and `[]`.
+    #[default]
+    Compact,
+    /// `{ }` and `[ ]`.
+    Spaced,
+    /// The closing bracket on a line of its own.
+    Expanded,
+}
+
+/// How the pretty-printers lay out arrays and objects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// Every element and member on a line of its own.
+    #[default]
+    Expanded,
+    /// Arrays and objects on one line, as `[1, 2]` and `{"a": 1}`, where they
+    /// fit in this many columns, and broken over lines where they do not.
+    MaxWidth(usize),
+}
+
+/// Settings for [`format_with`](crate::format_with) and
+/// [`format_value_with`](crate::format_value_with).
+///
+/// ```
+/// use rj::{FormatOptions, Newline};
+///
+/// let options = FormatOptions::new().indent("\t").newline(Newline::CrLf);
+/// assert_eq!(rj::format_with("[1]", &options).unwrap(), "[\r\n\t1\r\n]");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub(crate) indent: String,
+    pub(crate) newline: Newline,
+    pub(crate) trailing_newline: bool,
+    pub(crate) space_after_colon: bool,
+    pub(crate) empty_containers: EmptyContainers,
+    pub(crate) escape: Escape,
+    pub(crate) sort_keys: Option<KeyOrder>,
+    pub(crate) layout: Layout,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: "  ".to_string(),
+            newline: Newline::default(),
+            trailing_newline: false,
+            space_after_colon: true,
+            empty_containers: EmptyContainers::default(),
+            escape: Escape::default(),
+            sort_keys: None,
+            layout: Layout::default(),
+        }
+    }
+}
+
+impl FormatOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// What each level of nesting is indented with, such as four spaces or a
+    /// tab. Two spaces by default.
+    pub fn indent(mut self, indent: impl Into<String>) -> Self {
+        self.indent = indent.into();
+        self
+    }
+
+    pub fn newline(mut self, newline: Newline) -> Self {
+        self.newline = newline;
+        self
+    }
+
+    /// Ends the output with a line break, as most editors save files.
+    pub fn trailing_newline(mut self, yes: bool) -> Self {
+        self.trailing_newline = yes;
+        self
+    }
+
+    pub fn space_after_colon(mut self, yes: bool) -> Self {
+        self.space_after_colon = yes;
+        self
+    }
+
+    pub fn empty_containers(mut self, style: EmptyContainers) -> Self {
+        self.empty_containers = style;
+        self
+    }
+
+    pub fn escape(mut self, escape: Escape) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// Writes object members in `order` instead of the order they are in.
+    pub fn sort_keys(mut self, order: KeyOrder) -> Self {
+        self.sort_keys = Some(order);
+        self
+    }
+
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    fn colon(&self) -> &'static str {
+        if self.space_after_colon { ": " } else { ":" }
+    }
+
+    fn finish(&self, mut buf: String) -> String {
+        if self.trailing_newline {
+            buf.push_str(self.newline.as_str());
         }
+        buf
     }
 }
 
-pub(crate) fn format(value: &Value, indent: usize) -> String {
+pub(crate) fn format(value: &Value, options: &FormatOptions) -> String {
+    options.finish(match options.layout {
+        Layout::Expanded => pretty(value, options, 0),
+        Layout::MaxWidth(width) => render(&doc(value, options, false), options, width),
+    })
+}
+
+/// Pretty-prints `value`, which is nested in `depth` containers.
+fn pretty(value: &Value, options: &FormatOptions, depth: usize) -> String {
     match value {
-        Value::String(x) => format!("\"{x}\""),
+        Value::String(x) => quote(x, options.escape),
+        Value::Number(Number::Float(x)) if !x.is_finite() => "null".to_string(),
         Value::Number(x) => x.to_string(),
         Value::Boolean(x) => x.to_string(),
         Value::Null => "null".to_string(),
         Value::Object(obj) => {
-            if obj.is_empty() {
-                return "{}".to_string();
-            }
-            let mut buf = String::new();
-            buf.push_str("{\n");
-            buf.push_str(&" ".repeat(indent));
-            for (i, (k, v)) in obj.iter().enumerate() {
-                buf.push_str(&format!("\"{k}\""));
-                buf.push_str(": ");
-                buf.push_str(&format(v, indent + 2));
-                if i < obj.len() - 1 {
-                    buf.push_str(",\n");
-                    buf.push_str(&" ".repeat(indent));
-                }
+            let members = members_of(obj, options).into_iter().map(|(k, v)| {
+                let key = quote(k, options.escape);
+                format!("{key}{}{}", options.colon(), pretty(v, options, depth + 1))
+            });
+            container(['{', '}'], members.collect(), options, depth, false)
+        }
+        Value::Array(arr) => {
+            let elements = arr.iter().map(|v| pretty(v, options, depth + 1));
+            container(['[', ']'], elements.collect(), options, depth, false)
+        }
+    }
+}
+
+/// The members of `obj`, in the order `options` ask for.
+fn members_of<'a>(
+    obj: &'a IndexMap<String, Value>,
+    options: &FormatOptions,
+) -> Vec<(&'a String, &'a Value)> {
+    let mut members: Vec<_> = obj.iter().collect();
+    if let Some(order) = &options.sort_keys {
+        members.sort_by(|(a, _), (b, _)| order.compare(a, b));
+    }
+    members
+}
+
+/// Puts each of `entries` on a line of its own between `brackets`, with a
+/// comma after every entry but the last, or after every one with
+/// `trailing_commas`.
+fn container(
+    [open, close]: [char; 2],
+    entries: Vec<String>,
+    options: &FormatOptions,
+    depth: usize,
+    trailing_commas: bool,
+) -> String {
+    let newline = options.newline.as_str();
+    if entries.is_empty() {
+        return match options.empty_containers {
+            EmptyContainers::Compact => format!("{open}{close}"),
+            EmptyContainers::Spaced => format!("{open} {close}"),
+            EmptyContainers::Expanded => {
+                format!("{open}{newline}{}{close}", options.indent.repeat(depth))
             }
-            buf.push('\n');
-            buf.push_str(&" ".repeat(indent - 2));
-            buf.push('}');
-            buf
+        };
+    }
+    let mut buf = String::new();
+    buf.push(open);
+    for (i, entry) in entries.iter().enumerate() {
+        buf.push_str(newline);
+        buf.push_str(&options.indent.repeat(depth + 1));
+        buf.push_str(entry);
+        if trailing_commas || i + 1 < entries.len() {
+            buf.push(',');
+        }
+    }
+    buf.push_str(newline);
+    buf.push_str(&options.indent.repeat(depth));
+    buf.push(close);
+    buf
+}
+
+/// Like [`format`], but writes JSON5: member names that are identifiers stay
+/// unquoted, every member and element is followed by a comma, and non-finite
+/// numbers are written as `Infinity` and `NaN`.
+pub(crate) fn format_json5(value: &Value, options: &FormatOptions) -> String {
+    options.finish(match options.layout {
+        Layout::Expanded => pretty_json5(value, options, 0),
+        Layout::MaxWidth(width) => render(&doc(value, options, true), options, width),
+    })
+}
+
+fn pretty_json5(value: &Value, options: &FormatOptions, depth: usize) -> String {
+    match value {
+        Value::Number(Number::Float(x)) if x.is_nan() => "NaN".to_string(),
+        Value::Number(Number::Float(x)) if x.is_infinite() => {
+            if *x > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+        }
+        Value::Object(obj) => {
+            let members = members_of(obj, options).into_iter().map(|(k, v)| {
+                let key = json5_key(k, options);
+                let value = pretty_json5(v, options, depth + 1);
+                format!("{key}{}{value}", options.colon())
+            });
+            container(['{', '}'], members.collect(), options, depth, true)
         }
         Value::Array(arr) => {
-            if arr.is_empty() {
-                return "[]".to_string();
+            let elements = arr.iter().map(|v| pretty_json5(v, options, depth + 1));
+            container(['[', ']'], elements.collect(), options, depth, true)
+        }
+        _ => pretty(value, options, depth),
+    }
+}
+
+fn json5_key(key: &str, options: &FormatOptions) -> String {
+    if is_identifier(key) {
+        key.to_string()
+    } else {
+        quote(key, options.escape)
+    }
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// A document for [`render`] to lay out, after Wadler's "A prettier
+/// printer".
+enum Doc {
+    Text(String),
+    /// A line break, or `flat` where the enclosing group fits on one line.
+    Line {
+        flat: &'static str,
+    },
+    /// A line break that keeps the enclosing group from going on one line.
+    HardLine,
+    /// Text written only where the enclosing group is broken over lines.
+    IfBroken(&'static str),
+    /// Line breaks in these documents indent one level deeper.
+    Nest(Vec<Doc>),
+    /// Documents put on one line if they fit, and broken over lines if not.
+    Group(Vec<Doc>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Broken,
+}
+
+/// The document for `value`, written as JSON5 with `json5`.
+fn doc(value: &Value, options: &FormatOptions, json5: bool) -> Doc {
+    let (brackets, entries) = match value {
+        Value::Object(obj) => {
+            let members = members_of(obj, options).into_iter().map(|(k, v)| {
+                let key = if json5 {
+                    json5_key(k, options)
+                } else {
+                    quote(k, options.escape)
+                };
+                vec![
+                    Doc::Text(format!("{key}{}", options.colon())),
+                    doc(v, options, json5),
+                ]
+            });
+            (['{', '}'], members.collect::<Vec<_>>())
+        }
+        Value::Array(arr) => {
+            let elements = arr.iter().map(|v| vec![doc(v, options, json5)]);
+            (['[', ']'], elements.collect())
+        }
+        _ if json5 => return Doc::Text(pretty_json5(value, options, 0)),
+        _ => return Doc::Text(pretty(value, options, 0)),
+    };
+    let [open, close] = brackets;
+    if entries.is_empty() {
+        return match options.empty_containers {
+            EmptyContainers::Compact => Doc::Text(format!("{open}{close}")),
+            EmptyContainers::Spaced => Doc::Text(format!("{open} {close}")),
+            EmptyContainers::Expanded => Doc::Group(vec![
+                Doc::Text(open.to_string()),
+                Doc::HardLine,
+                Doc::Text(close.to_string()),
+            ]),
+        };
+    }
+    let mut nested = Vec::new();
+    for (i, entry) in entries.into_iter().enumerate() {
+        if i > 0 {
+            nested.push(Doc::Text(",".to_string()));
+        }
+        nested.push(Doc::Line {
+            flat: if i > 0 { " " } else { "" },
+        });
+        nested.extend(entry);
+    }
+    if json5 {
+        nested.push(Doc::IfBroken(","));
+    }
+    Doc::Group(vec![
+        Doc::Text(open.to_string()),
+        Doc::Nest(nested),
+        Doc::Line { flat: "" },
+        Doc::Text(close.to_string()),
+    ])
+}
+
+/// Lays out `doc`, putting each group on one line if it fits in `width`
+/// columns along with what follows it up to the next line break.
+fn render(doc: &Doc, options: &FormatOptions, width: usize) -> String {
+    let mut buf = String::new();
+    let mut column = 0;
+    let mut stack = vec![(0, Mode::Broken, doc)];
+    while let Some((depth, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => {
+                buf.push_str(text);
+                column += text.chars().count();
             }
-            let mut buf = String::new();
-            buf.push('[');
-            buf.push('\n');
-            buf.push_str(&" ".repeat(indent));
-            for (i, ele) in arr.iter().enumerate() {
-                buf.push_str(&format(ele, indent + 2));
-                if i < arr.len() - 1 {
-                    buf.push_str(",\n");
-                    buf.push_str(&" ".repeat(indent));
+            Doc::Line { flat } if mode == Mode::Flat => {
+                buf.push_str(flat);
+                column += flat.len();
+            }
+            Doc::Line { .. } | Doc::HardLine => {
+                let indent = options.indent.repeat(depth);
+                buf.push_str(options.newline.as_str());
+                buf.push_str(&indent);
+                column = indent.chars().count();
+            }
+            Doc::IfBroken(text) => {
+                if mode == Mode::Broken {
+                    buf.push_str(text);
+                    column += text.len();
                 }
             }
-            buf.push('\n');
-            buf.push_str(&" ".repeat(indent - 2));
-            buf.push(']');
-            buf
+            Doc::Nest(docs) => stack.extend(docs.iter().rev().map(|d| (depth + 1, mode, d))),
+            Doc::Group(docs) => {
+                let mode = if mode == Mode::Flat || fits(docs, &stack, width.saturating_sub(column))
+                {
+                    Mode::Flat
+                } else {
+                    Mode::Broken
+                };
+                stack.extend(docs.iter().rev().map(|d| (depth, mode, d)));
+            }
+        }
+    }
+    buf
+}
+
+/// Whether `docs` fit on one line in `width` columns, followed by what is
+/// left on `rest` up to its next line break.
+fn fits(docs: &[Doc], rest: &[(usize, Mode, &Doc)], mut width: usize) -> bool {
+    let mut rest = rest.iter().rev().map(|&(_, mode, doc)| (mode, doc));
+    let mut stack: Vec<_> = docs.iter().rev().map(|d| (Mode::Flat, d)).collect();
+    loop {
+        let Some((mode, doc)) = stack.pop().or_else(|| rest.next()) else {
+            return true;
+        };
+        let text = match doc {
+            Doc::Text(text) => text.as_str(),
+            Doc::Line { flat } if mode == Mode::Flat => flat,
+            Doc::Line { .. } => return true,
+            Doc::HardLine => return mode == Mode::Broken,
+            Doc::IfBroken(text) if mode == Mode::Broken => text,
+            Doc::IfBroken(_) => "",
+            Doc::Nest(docs) | Doc::Group(docs) => {
+                stack.extend(docs.iter().rev().map(|d| (mode, d)));
+                continue;
+            }
+        };
+        match width.checked_sub(text.chars().count()) {
+            Some(left) => width = left,
+            None => return false,
         }
     }
 }
@@ -101,6 +524,38 @@ mod generate_tests {
         assert_eq!(s, json);
     }
 
+    #[test]
+    fn non_finite_number() {
+        let options = crate::ParseOptions::json5();
+        let value = crate::parse_with("[Infinity, -Infinity, NaN]", &options).unwrap();
+        assert_eq!(value.to_string(), "[null,null,null]");
+        assert_eq!(
+            format(&value, &FormatOptions::new()),
+            "[\n  null,\n  null,\n  null\n]"
+        );
+        let options = FormatOptions::new().layout(Layout::MaxWidth(80));
+        assert_eq!(format(&value, &options), "[null, null, null]");
+        assert_eq!(format_json5(&value, &options), "[Infinity, -Infinity, NaN]");
+    }
+
+    #[test]
+    fn large_integer() {
+        let json = "[9007199254740993,-9223372036854775808]";
+        let s = Value::from(json).to_string();
+        assert_eq!(s, json);
+        assert_eq!(
+            format(&json.into(), &FormatOptions::new()),
+            "[\n  9007199254740993,\n  -9223372036854775808\n]"
+        );
+    }
+
+    #[test]
+    fn integral_float() {
+        let json = "[1.0,1e2]";
+        let s = Value::from(json).to_string();
+        assert_eq!(s, "[1.0,100.0]");
+    }
+
     #[test]
     fn boolean() {
         let json = r#"false"#;
@@ -141,6 +596,95 @@ mod generate_tests {
     }
 }
 
+#[cfg(test)]
+mod quote_tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn escapes() {
+        assert_eq!(quote("plain é 😀", Escape::Minimal), r#""plain é 😀""#);
+        assert_eq!(quote("\"\\/", Escape::Minimal), r#""\"\\/""#);
+        assert_eq!(quote("\n\r\t\x08\x0C", Escape::Minimal), r#""\n\r\t\b\f""#);
+        assert_eq!(
+            quote("\0\x1F\x7F", Escape::Minimal),
+            "\"\\u0000\\u001f\x7F\""
+        );
+    }
+
+    #[test]
+    fn policies() {
+        let s = "<a href='x'>&</a> é 😀 \u{2028}\u{2029}";
+        assert_eq!(quote(s, Escape::Minimal), format!("\"{s}\""));
+        assert_eq!(
+            quote(s, Escape::Ascii),
+            r#""<a href='x'>&</a> \u00e9 \ud83d\ude00 \u2028\u2029""#
+        );
+        assert_eq!(
+            quote(s, Escape::Html),
+            r#""\u003ca href=\u0027x\u0027\u003e\u0026\u003c/a\u003e é 😀 \u2028\u2029""#
+        );
+        assert_eq!(
+            quote(s, Escape::JavaScript),
+            r#""<a href='x'>&</a> é 😀 \u2028\u2029""#
+        );
+    }
+
+    #[test]
+    fn writers_escape_keys_and_values() {
+        let mut obj = IndexMap::new();
+        obj.insert("a\"b".to_string(), Value::String("line\nbreak".into()));
+        let value = Value::Object(obj);
+        assert_eq!(value.to_string(), r#"{"a\"b":"line\nbreak"}"#);
+        assert_eq!(
+            format(&value, &FormatOptions::new()),
+            "{\n  \"a\\\"b\": \"line\\nbreak\"\n}"
+        );
+        assert_eq!(
+            format_json5(&value, &FormatOptions::new()),
+            "{\n  \"a\\\"b\": \"line\\nbreak\",\n}"
+        );
+    }
+
+    fn value() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![
+            Just(()).prop_map(|()| Value::Null),
+            any::<bool>().prop_map(Value::Boolean),
+            any::<i64>().prop_map(|n| Value::Number(n.into())),
+            any::<String>().prop_map(Value::String),
+        ];
+        leaf.prop_recursive(4, 32, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+                prop::collection::vec((any::<String>(), inner), 0..4)
+                    .prop_map(|members| Value::Object(members.into_iter().collect())),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn strings_round_trip(s in any::<String>()) {
+            let value = Value::String(s);
+            for escape in [Escape::Minimal, Escape::Ascii, Escape::Html, Escape::JavaScript] {
+                let options = FormatOptions::new().escape(escape);
+                prop_assert_eq!(&crate::parse(&stringify(&value, &options)).unwrap(), &value);
+            }
+            prop_assert!(stringify(&value, &FormatOptions::new().escape(Escape::Ascii)).is_ascii());
+        }
+
+        #[test]
+        fn values_round_trip(value in value(), width in 0..40usize) {
+            prop_assert_eq!(&crate::parse(&value.to_string()).unwrap(), &value);
+            prop_assert_eq!(&crate::parse(&format(&value, &FormatOptions::new())).unwrap(), &value);
+            let json5 = crate::parse_with(&format_json5(&value, &FormatOptions::new()), &crate::ParseOptions::json5());
+            prop_assert_eq!(&json5.unwrap(), &value);
+            let options = FormatOptions::new().layout(Layout::MaxWidth(width));
+            prop_assert_eq!(&crate::parse(&format(&value, &options)).unwrap(), &value);
+        }
+    }
+}
+
 #[cfg(test)]
 mod format_tests {
     use super::*;
@@ -148,14 +692,14 @@ mod format_tests {
     #[test]
     fn test_object() {
         let json = r#"{"key":"value"}"#;
-        let formatted = format(&json.into(), 2);
+        let formatted = format(&json.into(), &FormatOptions::new());
         assert_eq!(formatted, "{\n  \"key\": \"value\"\n}");
     }
 
     #[test]
     fn test_nested_object() {
         let json = r#"{"key":{"key2":"value2"}}"#;
-        let formatted = format(&json.into(), 2);
+        let formatted = format(&json.into(), &FormatOptions::new());
         assert_eq!(
             formatted,
             "{\n  \"key\": {\n    \"key2\": \"value2\"\n  }\n}"
@@ -165,28 +709,28 @@ mod format_tests {
     #[test]
     fn test_empty_object() {
         let json = r#"{}"#;
-        let formatted = format(&json.into(), 2);
+        let formatted = format(&json.into(), &FormatOptions::new());
         assert_eq!(formatted, "{}");
     }
 
     #[test]
     fn test_nested_empty_object() {
         let json = r#"{"key": {}}"#;
-        let formatted = format(&json.into(), 2);
+        let formatted = format(&json.into(), &FormatOptions::new());
         assert_eq!(formatted, "{\n  \"key\": {}\n}");
     }
 
     #[test]
     fn test_array() {
         let json = r#"[1,2,3]"#;
-        let formatted = format(&json.into(), 2);
+        let formatted = format(&json.into(), &FormatOptions::new());
         assert_eq!(formatted, "[\n  1,\n  2,\n  3\n]");
     }
 
     #[test]
     fn test_nested_array() {
         let json = r#"[1,[2,[3]]]"#;
-        let formatted = format(&json.into(), 2);
+        let formatted = format(&json.into(), &FormatOptions::new());
         assert_eq!(
             formatted,
             "[\n  1,\n  [\n    2,\n    [\n      3\n    ]\n  ]\n]"
@@ -196,14 +740,221 @@ mod format_tests {
     #[test]
     fn test_empty_array() {
         let json = r#"[]"#;
-        let formatted = format(&json.into(), 2);
+        let formatted = format(&json.into(), &FormatOptions::new());
         assert_eq!(formatted, "[]");
     }
 
     #[test]
     fn test_nested_empty_array() {
         let json = r#"[[],[[]]]"#;
-        let formatted = format(&json.into(), 2);
+        let formatted = format(&json.into(), &FormatOptions::new());
         assert_eq!(formatted, "[\n  [],\n  [\n    []\n  ]\n]");
     }
 }
+
+#[cfg(test)]
+mod format_options_tests {
+    use super::*;
+
+    const JSON: &str = r#"{"a":[1,{}],"b":[]}"#;
+
+    #[test]
+    fn indent() {
+        let options = FormatOptions::new().indent("    ");
+        assert_eq!(
+            format(&JSON.into(), &options),
+            "{\n    \"a\": [\n        1,\n        {}\n    ],\n    \"b\": []\n}"
+        );
+        let options = FormatOptions::new().indent("\t");
+        assert_eq!(
+            format(&JSON.into(), &options),
+            "{\n\t\"a\": [\n\t\t1,\n\t\t{}\n\t],\n\t\"b\": []\n}"
+        );
+    }
+
+    #[test]
+    fn newlines() {
+        let options = FormatOptions::new()
+            .newline(Newline::CrLf)
+            .trailing_newline(true);
+        assert_eq!(format(&"[1]".into(), &options), "[\r\n  1\r\n]\r\n");
+        let options = FormatOptions::new().trailing_newline(true);
+        assert_eq!(format(&"1".into(), &options), "1\n");
+    }
+
+    #[test]
+    fn colon_and_empty_containers() {
+        let options = FormatOptions::new()
+            .space_after_colon(false)
+            .empty_containers(EmptyContainers::Spaced);
+        assert_eq!(
+            format(&JSON.into(), &options),
+            "{\n  \"a\":[\n    1,\n    { }\n  ],\n  \"b\":[ ]\n}"
+        );
+        let options = FormatOptions::new().empty_containers(EmptyContainers::Expanded);
+        assert_eq!(
+            format(&JSON.into(), &options),
+            "{\n  \"a\": [\n    1,\n    {\n    }\n  ],\n  \"b\": [\n  ]\n}"
+        );
+        assert_eq!(format(&"{}".into(), &options), "{\n}");
+    }
+
+    #[test]
+    fn sort_keys() {
+        let value = Value::from(r#"{"version": 1, "deps": {"b": 1, "a": 2}, "name": "rj"}"#);
+        let options = FormatOptions::new().sort_keys(KeyOrder::CodePoint);
+        assert_eq!(
+            stringify(&value, &options),
+            r#"{"deps":{"a":2,"b":1},"name":"rj","version":1}"#
+        );
+        let order = KeyOrder::Priority(vec!["name".into(), "version".into()]);
+        let options = FormatOptions::new().sort_keys(order);
+        assert_eq!(
+            format(&value, &options),
+            "{\n  \"name\": \"rj\",\n  \"version\": 1,\n  \"deps\": {\n    \"a\": 2,\n    \"b\": 1\n  }\n}"
+        );
+        assert_eq!(
+            format_json5(&value, &options.indent("")),
+            "{\nname: \"rj\",\nversion: 1,\ndeps: {\na: 2,\nb: 1,\n},\n}"
+        );
+        // The value itself keeps its order.
+        assert_eq!(
+            value.to_string(),
+            r#"{"version":1,"deps":{"b":1,"a":2},"name":"rj"}"#
+        );
+    }
+
+    #[test]
+    fn json5() {
+        let options = FormatOptions::new().indent("\t").space_after_colon(false);
+        assert_eq!(
+            format_json5(&JSON.into(), &options),
+            "{\n\ta:[\n\t\t1,\n\t\t{},\n\t],\n\tb:[],\n}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod layout_tests {
+    use super::*;
+
+    fn width(width: usize) -> FormatOptions {
+        FormatOptions::new().layout(Layout::MaxWidth(width))
+    }
+
+    #[test]
+    fn fits_on_one_line() {
+        let value = r#"{"a":[1,2,3],"b":{"c":null}}"#.into();
+        let one_line = r#"{"a": [1, 2, 3], "b": {"c": null}}"#;
+        assert_eq!(format(&value, &width(one_line.len())), one_line);
+        assert_eq!(
+            format(&value, &width(one_line.len() - 1)),
+            "{\n  \"a\": [1, 2, 3],\n  \"b\": {\"c\": null}\n}"
+        );
+    }
+
+    #[test]
+    fn counts_what_follows() {
+        // `[1, 2],` is 9 columns wide with its indent and comma.
+        let value = "[[1,2],[3]]".into();
+        assert_eq!(format(&value, &width(9)), "[\n  [1, 2],\n  [3]\n]");
+        assert_eq!(
+            format(&value, &width(8)),
+            "[\n  [\n    1,\n    2\n  ],\n  [3]\n]"
+        );
+    }
+
+    #[test]
+    fn zero_width_matches_expanded() {
+        for json in ["1", "[]", r#"{"a":[1,{}],"b":[[]]}"#] {
+            for style in [
+                EmptyContainers::Compact,
+                EmptyContainers::Spaced,
+                EmptyContainers::Expanded,
+            ] {
+                let expanded = FormatOptions::new().empty_containers(style);
+                let value = json.into();
+                assert_eq!(
+                    format(&value, &width(0).empty_containers(style)),
+                    format(&value, &expanded)
+                );
+                assert_eq!(
+                    format_json5(&value, &width(0).empty_containers(style)),
+                    format_json5(&value, &expanded)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn expanded_empty_containers_break() {
+        let options = width(80).empty_containers(EmptyContainers::Expanded);
+        assert_eq!(
+            format(&r#"[[1],{}]"#.into(), &options),
+            "[\n  [1],\n  {\n  }\n]"
+        );
+    }
+
+    #[test]
+    fn json5() {
+        let value = r#"{"a":[1,2],"b c":true}"#.into();
+        assert_eq!(
+            format_json5(&value, &width(80)),
+            r#"{a: [1, 2], "b c": true}"#
+        );
+        assert_eq!(
+            format_json5(&value, &width(12)),
+            "{\n  a: [1, 2],\n  \"b c\": true,\n}"
+        );
+    }
+
+    #[test]
+    fn options_apply() {
+        let options = width(10)
+            .indent("\t")
+            .newline(Newline::CrLf)
+            .space_after_colon(false)
+            .trailing_newline(true);
+        assert_eq!(
+            format(&r#"{"a":[1],"b":2}"#.into(), &options),
+            "{\r\n\t\"a\":[1],\r\n\t\"b\":2\r\n}\r\n"
+        );
+    }
+}
+
+#[cfg(test)]
+mod format_json5_tests {
+    use super::*;
+    use crate::ParseOptions;
+
+    #[test]
+    fn object() {
+        let json = r#"{"key": "value", "two words": [1, {}], "$x_1": {"a": []}}"#;
+        assert_eq!(
+            format_json5(&json.into(), &FormatOptions::new()),
+            "{\n  key: \"value\",\n  \"two words\": [\n    1,\n    {},\n  ],\n  $x_1: {\n    a: [],\n  },\n}"
+        );
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!(format_json5(&"[]".into(), &FormatOptions::new()), "[]");
+        assert_eq!(format_json5(&"1.5".into(), &FormatOptions::new()), "1.5");
+        let v = crate::parse_with("[Infinity, -Infinity, NaN]", &ParseOptions::json5()).unwrap();
+        assert_eq!(
+            format_json5(&v, &FormatOptions::new()),
+            "[\n  Infinity,\n  -Infinity,\n  NaN,\n]"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let json5 = "{a: [0x10, 'b', .5], 'c d': {e: null,},}";
+        let v = crate::parse_with(json5, &ParseOptions::json5()).unwrap();
+        let written = format_json5(&v, &FormatOptions::new());
+        assert_eq!(
+            crate::parse_with(&written, &ParseOptions::json5()).unwrap(),
+            v
+        );
+    }
+}
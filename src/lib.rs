@@ -1,19 +1,183 @@
 // Defined in RFC8259 also known as STD90.
 
-use value::Value;
+pub use generate::{EmptyContainers, Escape, FormatOptions, Layout, Newline};
+pub use number::Number;
+pub use parse::ParseOptions;
+pub use value::{BorrowedValue, KeyOrder, Value};
 
+pub mod cst;
+mod encoding;
 mod generate;
+pub mod ndjson;
+mod number;
 pub mod parse;
+pub mod read;
+pub mod reader;
+pub mod repair;
 mod value;
 
+/// Parses `input` with the default [`ParseOptions`], which reject values
+/// nested in more than 128 arrays and objects. Use [`parse_with`] and
+/// [`ParseOptions::max_depth`] to accept deeper ones.
 pub fn parse(input: &str) -> Result<Value, parse::Error> {
     parse::parse(input)
 }
 
+pub fn parse_with(input: &str, options: &ParseOptions) -> Result<Value, parse::Error> {
+    parse::parse_with(input, options)
+}
+
+/// Parses JSON bytes in UTF-8, UTF-16 or UTF-32, with or without a byte
+/// order mark. UTF-8 is checked strictly; error offsets count bytes of
+/// `input`.
+pub fn parse_bytes(input: &[u8]) -> Result<Value, parse::Error> {
+    parse::parse_bytes(input, &ParseOptions::new())
+}
+
+pub fn parse_bytes_with(input: &[u8], options: &ParseOptions) -> Result<Value, parse::Error> {
+    parse::parse_bytes(input, options)
+}
+
+/// Decodes JSON bytes to text the way [`parse_bytes`] does, for use with
+/// the other functions that take `&str`.
+pub fn decode(input: &[u8]) -> Result<std::borrow::Cow<'_, str>, parse::Error> {
+    Ok(encoding::decode(input)?.text)
+}
+
+/// Like [`decode`], but decodes as `reader` is read, for use with
+/// [`parse_reader_with`]. The encoding is worked out from the first four
+/// bytes. Error offsets then count bytes of the decoded UTF-8.
+pub fn decode_reader<R: std::io::Read>(reader: R) -> std::io::Result<impl std::io::Read> {
+    encoding::decode_reader(reader)
+}
+
+/// Parses `input` into a syntax tree for editing it without losing its
+/// formatting or comments. See [`cst`].
+pub fn parse_cst(input: &str) -> Result<cst::Document, parse::Error> {
+    cst::parse(input, &ParseOptions::new())
+}
+
+pub fn parse_cst_with(input: &str, options: &ParseOptions) -> Result<cst::Document, parse::Error> {
+    cst::parse(input, options)
+}
+
+/// Parses JSON without copying strings that contain no escape sequences out of
+/// `input`.
+pub fn parse_borrowed(input: &str) -> Result<BorrowedValue<'_>, parse::Error> {
+    parse::parse_borrowed(input)
+}
+
+/// Parses JSON from `reader` as it is read, without loading the whole input
+/// into memory first.
+pub fn parse_reader<R: std::io::Read>(reader: R) -> Result<Value, parse::Error> {
+    parse::parse_reader(reader)
+}
+
+pub fn parse_reader_with<R: std::io::Read>(
+    reader: R,
+    options: &ParseOptions,
+) -> Result<Value, parse::Error> {
+    parse::parse_reader_with(reader, options)
+}
+
+/// Reads JSON documents one after another from `input`, such as the output
+/// of `docker events`. See [`parse::Documents`].
+pub fn parse_documents(input: &str) -> parse::Documents<'_, read::StrRead<'_>> {
+    parse::Documents::new(reader::Reader::new(input))
+}
+
+pub fn parse_reader_documents<R: std::io::Read>(
+    reader: R,
+) -> parse::Documents<'static, read::IoRead<R>> {
+    parse::Documents::new(reader::Reader::from_reader(reader))
+}
+
+/// Parses a document that may be cut off, such as a response that is still
+/// streaming in. Open strings, arrays and objects are closed where the input
+/// ends; other errors are reported as usual.
+pub fn parse_partial(input: &str) -> Result<parse::Partial, parse::Error> {
+    parse::parse_partial(input, &ParseOptions::new())
+}
+
+pub fn parse_partial_with(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<parse::Partial, parse::Error> {
+    parse::parse_partial(input, options)
+}
+
+/// Parses as much of `input` as possible instead of stopping at the first
+/// error.
+///
+/// After an error, reading resumes at the next `,` or closing bracket. Values
+/// that could not be read are left out, or read as `null` where a member name
+/// was read. Returns every error found, in input order.
+pub fn parse_recovering(input: &str) -> (Value, Vec<parse::Error>) {
+    parse::parse_recovering(input, &ParseOptions::new())
+}
+
+pub fn parse_recovering_with(input: &str, options: &ParseOptions) -> (Value, Vec<parse::Error>) {
+    parse::parse_recovering(input, options)
+}
+
+pub fn parse_reader_recovering_with<R: std::io::Read>(
+    reader: R,
+    options: &ParseOptions,
+) -> (Value, Vec<parse::Error>) {
+    parse::parse_reader_recovering(reader, options)
+}
+
+/// Applies heuristic fixes to almost-JSON. See [`repair::repair`].
+pub fn repair(input: &str) -> (String, Vec<repair::Fix>) {
+    repair::repair(input)
+}
+
 pub fn stringify(value: &Value) -> String {
     value.to_string()
 }
 
+/// Writes `value` compactly. Of `options`, only the escaping and key order
+/// apply.
+pub fn stringify_with(value: &Value, options: &FormatOptions) -> String {
+    generate::stringify(value, options)
+}
+
+/// Pretty-prints `input`. Numbers keep the digits they were written with.
 pub fn format(input: &str) -> Result<String, parse::Error> {
-    Ok(generate::format(&parse(input)?, 2))
+    let options = ParseOptions::new().for_formatting();
+    Ok(format_value(&parse_with(input, &options)?))
+}
+
+/// Pretty-prints JSON with comments and trailing commas, such as
+/// `tsconfig.json`. The output is plain JSON: comments are dropped.
+pub fn format_jsonc(input: &str) -> Result<String, parse::Error> {
+    let options = ParseOptions::jsonc().for_formatting();
+    Ok(format_value(&parse_with(input, &options)?))
+}
+
+/// Pretty-prints `input` laid out as `options` say. Numbers keep the digits
+/// they were written with.
+pub fn format_with(input: &str, options: &FormatOptions) -> Result<String, parse::Error> {
+    let parse_options = ParseOptions::new().for_formatting();
+    Ok(format_value_with(
+        &parse_with(input, &parse_options)?,
+        options,
+    ))
+}
+
+pub fn format_value(value: &Value) -> String {
+    generate::format(value, &FormatOptions::new())
+}
+
+pub fn format_value_with(value: &Value, options: &FormatOptions) -> String {
+    generate::format(value, options)
+}
+
+/// Pretty-prints `value` as JSON5.
+pub fn format_value_json5(value: &Value) -> String {
+    generate::format_json5(value, &FormatOptions::new())
+}
+
+pub fn format_value_json5_with(value: &Value, options: &FormatOptions) -> String {
+    generate::format_json5(value, options)
 }
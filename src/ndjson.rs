@@ -0,0 +1,332 @@
+//! Newline-delimited JSON, also known as JSON Lines: one document per line.
+//!
+//! ```
+//! use rj::ndjson::{Reader, Writer};
+//!
+//! let input = "{\"id\": 1}\n{\"id\": 2}\n";
+//! let mut writer = Writer::new(Vec::new());
+//! for value in Reader::new(input.as_bytes()) {
+//!     writer.write(&value.unwrap()).unwrap();
+//! }
+//! assert_eq!(writer.into_inner(), b"{\"id\":1}\n{\"id\":2}\n");
+//! ```
+
+use std::io::{self, BufRead, Read, Write};
+
+use crate::parse::{EXCERPT_LEN, Error, Position, Result};
+use crate::{FormatOptions, ParseOptions, Value};
+
+/// What a [`Reader`] does with lines that fail to parse.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BadLines {
+    /// Yield the error and go on with the next line.
+    #[default]
+    Yield,
+    /// Leave the line out.
+    Skip,
+    /// Leave the line out and keep the error for [`Reader::errors`].
+    Collect,
+}
+
+/// Iterates over the documents of newline-delimited JSON, skipping blank
+/// lines.
+///
+/// Error positions count lines and offsets from the start of the input. An
+/// I/O error ends reading, and is yielded whatever the [`BadLines`] policy.
+pub struct Reader<R> {
+    reader: R,
+    options: ParseOptions,
+    bad_lines: BadLines,
+    errors: Vec<Error>,
+    buf: Vec<u8>,
+    /// Number of the line last read.
+    line: usize,
+    /// Offset of the line after the one last read.
+    offset: usize,
+    done: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            options: ParseOptions::default(),
+            bad_lines: BadLines::default(),
+            errors: Vec::new(),
+            buf: Vec::new(),
+            line: 0,
+            offset: 0,
+            done: false,
+        }
+    }
+
+    /// Options for parsing each line. Limits apply per line.
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn bad_lines(mut self, policy: BadLines) -> Self {
+        self.bad_lines = policy;
+        self
+    }
+
+    /// Number of the line the last item came from, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Errors of the lines left out with [`BadLines::Collect`].
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    pub fn into_errors(self) -> Vec<Error> {
+        self.errors
+    }
+
+    /// Reads up to the next non-blank line and parses it.
+    fn next_line(&mut self) -> Option<Result<Value>> {
+        loop {
+            self.buf.clear();
+            let start = self.offset;
+            // Room for the longest line allowed and its line break, so a
+            // longer one is not read into memory whole.
+            let limit = self.options.max_input_size.saturating_add(2);
+            let mut line = (&mut self.reader).take(limit as u64);
+            match line.read_until(b'\n', &mut self.buf) {
+                Ok(0) => return None,
+                Ok(n) if n == limit && self.buf.last() != Some(&b'\n') => {
+                    self.line += 1;
+                    let at = self.options.max_input_size;
+                    let position = self.line_position(start, &self.buf, at);
+                    match self.reader.skip_until(b'\n') {
+                        Ok(rest) => self.offset += n + rest,
+                        Err(_) => self.done = true,
+                    }
+                    return Some(Err(Error::InputTooLarge(position)));
+                }
+                Ok(n) => self.offset += n,
+                Err(e) => {
+                    // Reading on might fail forever.
+                    self.done = true;
+                    self.line += 1;
+                    let position = self.line_position(start, &self.buf, self.buf.len());
+                    return Some(Err(Error::Io(e.to_string(), position)));
+                }
+            }
+            self.line += 1;
+            let line = self.buf.strip_suffix(b"\n").unwrap_or(&self.buf);
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            let line = match std::str::from_utf8(line) {
+                Ok(line) => line,
+                Err(e) => {
+                    let position = self.line_position(start, &self.buf, e.valid_up_to());
+                    return Some(Err(Error::InvalidUtf8(position)));
+                }
+            };
+            if line.trim_ascii().is_empty() {
+                continue;
+            }
+            return Some(crate::parse_with(line, &self.options).map_err(|mut e| {
+                let p = e.position_mut();
+                p.offset += start;
+                p.line = self.line;
+                e
+            }));
+        }
+    }
+
+    /// Position of byte `at` of `line`, which starts at `start`.
+    fn line_position(&self, start: usize, line: &[u8], at: usize) -> Position {
+        let before = String::from_utf8_lossy(&line[..at]);
+        Position {
+            offset: start + at,
+            line: self.line,
+            column: at + 1,
+            column_utf16: before.encode_utf16().count() + 1,
+            excerpt: String::from_utf8_lossy(&line[at..])
+                .chars()
+                .take(EXCERPT_LEN)
+                .collect(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match self.next_line()? {
+                Err(e) if self.bad_lines == BadLines::Skip && !self.done => drop(e),
+                Err(e) if self.bad_lines == BadLines::Collect && !self.done => self.errors.push(e),
+                res => return Some(res),
+            }
+        }
+        None
+    }
+}
+
+/// Writes values as newline-delimited JSON.
+pub struct Writer<W> {
+    writer: W,
+    options: FormatOptions,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            options: FormatOptions::default(),
+        }
+    }
+
+    /// Options for writing each value. Only the escaping and key order
+    /// apply, as output is always compact.
+    pub fn with_options(mut self, options: FormatOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Writes `value` compactly, followed by a newline.
+    pub fn write(&mut self, value: &Value) -> io::Result<()> {
+        writeln!(
+            self.writer,
+            "{}",
+            crate::stringify_with(value, &self.options)
+        )
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "{\"a\": 1}\n\n  \r\n[true,\n{\"b\": \"c\"}\r\n nul\n2";
+
+    #[test]
+    fn read_lines() {
+        let mut reader = Reader::new(INPUT.as_bytes());
+        assert_eq!(reader.next().unwrap().unwrap().to_string(), r#"{"a":1}"#);
+        assert_eq!(reader.line(), 1);
+
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(err, Error::UnexpectedToken(_)));
+        assert_eq!(reader.line(), 4);
+        assert_eq!(err.position().line, 4);
+        assert_eq!(err.position().offset, 20);
+        assert_eq!(err.position().column, 7);
+
+        assert_eq!(reader.next().unwrap().unwrap().to_string(), r#"{"b":"c"}"#);
+        assert_eq!(reader.line(), 5);
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.position().offset, 34);
+        assert_eq!(err.position().line, 6);
+        assert_eq!(reader.next().unwrap().unwrap().to_string(), "2");
+        assert_eq!(reader.line(), 7);
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn skip_and_collect_bad_lines() {
+        let values: Vec<_> = Reader::new(INPUT.as_bytes())
+            .bad_lines(BadLines::Skip)
+            .map(|v| v.unwrap().to_string())
+            .collect();
+        assert_eq!(values, [r#"{"a":1}"#, r#"{"b":"c"}"#, "2"]);
+
+        let mut reader = Reader::new(INPUT.as_bytes()).bad_lines(BadLines::Collect);
+        assert_eq!(reader.by_ref().count(), 3);
+        let lines: Vec<_> = reader.errors().iter().map(|e| e.position().line).collect();
+        assert_eq!(lines, [4, 6]);
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let input = b"1\n\"a\xFFb\"\n3\n";
+        let results: Vec<_> = Reader::new(&input[..]).collect();
+        assert_eq!(results.len(), 3);
+        let err = results[1].as_ref().unwrap_err();
+        assert!(matches!(err, Error::InvalidUtf8(_)));
+        assert_eq!(err.position().offset, 4);
+        assert_eq!(err.position().line, 2);
+        assert_eq!(err.position().column, 3);
+    }
+
+    #[test]
+    fn io_error_ends_reading() {
+        struct Failing;
+        impl io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("boom"))
+            }
+        }
+        let mut reader = Reader::new(io::BufReader::new(Failing)).bad_lines(BadLines::Skip);
+        assert!(matches!(reader.next(), Some(Err(Error::Io(ref s, _))) if s == "boom"));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn options_apply_per_line() {
+        let options = ParseOptions::jsonc().max_depth(1);
+        let results: Vec<_> = Reader::new("[1,] // one\n[[2]]\n".as_bytes())
+            .with_options(options)
+            .collect();
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(Error::DepthLimitExceeded(_))));
+    }
+
+    #[test]
+    fn long_lines_are_not_buffered() {
+        let input = format!("{}\n[1]\n", "1".repeat(100_000));
+        let options = ParseOptions::new().max_input_size(8);
+        let mut reader = Reader::new(input.as_bytes()).with_options(options);
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(err, Error::InputTooLarge(ref p) if p.offset == 8 && p.line == 1));
+        assert!(reader.buf.len() <= 10);
+        assert_eq!(reader.next().unwrap().unwrap(), Value::from("[1]"));
+        assert_eq!(reader.line(), 2);
+
+        let options = ParseOptions::new().max_input_size(3);
+        let results: Vec<_> = Reader::new("[1]\r\n[22]\n".as_bytes())
+            .with_options(options)
+            .collect();
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(Error::InputTooLarge(ref p)) if p.offset == 8));
+    }
+
+    #[test]
+    fn write_lines() {
+        let mut writer = Writer::new(Vec::new());
+        for json in [r#"{"a": [1, 2]}"#, "null", "  \"x\" "] {
+            writer.write(&json.into()).unwrap();
+        }
+        let out = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(out, "{\"a\":[1,2]}\nnull\n\"x\"\n");
+        let read: Vec<_> = Reader::new(out.as_bytes()).map(|v| v.unwrap()).collect();
+        assert_eq!(read.len(), 3);
+
+        let options = FormatOptions::new().escape(crate::Escape::Ascii);
+        let mut writer = Writer::new(Vec::new()).with_options(options);
+        writer.write(&Value::String("\u{2028}é".into())).unwrap();
+        assert_eq!(writer.into_inner(), b"\"\\u2028\\u00e9\"\n");
+
+        let options = FormatOptions::new().sort_keys(crate::KeyOrder::CodePoint);
+        let mut writer = Writer::new(Vec::new()).with_options(options);
+        writer.write(&r#"{"b": 1, "a": 2}"#.into()).unwrap();
+        assert_eq!(writer.into_inner(), b"{\"a\":2,\"b\":1}\n");
+    }
+}
@@ -0,0 +1,264 @@
+/// A JSON number.
+///
+/// Integers are kept as integers so that values beyond 2^53 survive a round
+/// trip. The parser stores non-negative integers as `PosInt` and negative ones
+/// as `NegInt`; everything else, and integers that overflow 64 bits, become
+/// `Float`.
+///
+/// With [`ParseOptions::preserve_number_lexemes`](crate::ParseOptions::preserve_number_lexemes)
+/// every number is kept as `Lexeme` instead, which converts on demand and is
+/// written back exactly as it was read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Number {
+    PosInt(u64),
+    /// Always less than zero.
+    NegInt(i64),
+    Float(f64),
+    /// The text of a number as it appeared in the input.
+    Lexeme(String),
+}
+
+impl Number {
+    /// Converts a number lexeme, falling back to `f64` for fractions,
+    /// exponents and integers that do not fit in 64 bits.
+    ///
+    /// Returns `None` if the lexeme is malformed or too large for `f64`.
+    pub(crate) fn from_lexeme(s: &str) -> Option<Self> {
+        let is_integer = !s.contains(['.', 'e', 'E']);
+        if is_integer && s != "-0" {
+            if let Ok(n) = s.parse::<u64>() {
+                return Some(Number::PosInt(n));
+            }
+            match s.parse::<i64>() {
+                Ok(n) if n < 0 => return Some(Number::NegInt(n)),
+                _ => {}
+            }
+        }
+        s.parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite())
+            .map(Number::Float)
+    }
+
+    pub fn is_i64(&self) -> bool {
+        self.as_i64().is_some()
+    }
+
+    pub fn is_u64(&self) -> bool {
+        self.as_u64().is_some()
+    }
+
+    /// Whether the number has a fraction or exponent, or is an integer too
+    /// large for 64 bits.
+    pub fn is_f64(&self) -> bool {
+        match self {
+            Number::Float(_) => true,
+            Number::Lexeme(s) => !matches!(
+                Number::from_lexeme(s),
+                Some(Number::PosInt(_) | Number::NegInt(_))
+            ),
+            _ => false,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_i128().and_then(|n| i64::try_from(n).ok())
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_i128().and_then(|n| u64::try_from(n).ok())
+    }
+
+    /// The number as `i128` if it was written as an integer that fits.
+    pub fn as_i128(&self) -> Option<i128> {
+        match self {
+            Number::PosInt(n) => Some(*n as i128),
+            Number::NegInt(n) => Some(*n as i128),
+            Number::Float(_) => None,
+            Number::Lexeme(s) if s.contains(['.', 'e', 'E']) => None,
+            Number::Lexeme(s) => s.parse().ok(),
+        }
+    }
+
+    /// The number as `f64`, rounding to the nearest representable value.
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            Number::PosInt(n) => *n as f64,
+            Number::NegInt(n) => *n as f64,
+            Number::Float(n) => *n,
+            Number::Lexeme(s) => s.parse().unwrap_or(f64::NAN),
+        }
+    }
+
+    /// Splits the number into a significand and a power of ten so that it
+    /// equals `significand * 10^exponent` exactly.
+    ///
+    /// Returns `None` if the significand does not fit in an `i128`.
+    pub fn as_decimal(&self) -> Option<(i128, i32)> {
+        let s = match self {
+            Number::Lexeme(s) => s.clone(),
+            n => n.to_string(),
+        };
+        let (mantissa, exp) = match s.find(['e', 'E']) {
+            Some(i) => (&s[..i], s[i + 1..].parse::<i32>().ok()?),
+            None => (&s[..], 0),
+        };
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let frac = frac.trim_end_matches('0');
+        let significand = format!("{int}{frac}").parse::<i128>().ok()?;
+        let exponent = exp.checked_sub(i32::try_from(frac.len()).ok()?)?;
+        Some((significand, exponent))
+    }
+}
+
+impl From<u64> for Number {
+    fn from(n: u64) -> Self {
+        Number::PosInt(n)
+    }
+}
+
+impl From<i64> for Number {
+    fn from(n: i64) -> Self {
+        if n < 0 {
+            Number::NegInt(n)
+        } else {
+            Number::PosInt(n as u64)
+        }
+    }
+}
+
+impl From<f64> for Number {
+    fn from(n: f64) -> Self {
+        Number::Float(n)
+    }
+}
+
+impl std::fmt::Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Number::Lexeme(s) => write!(f, "{s}"),
+            Number::PosInt(n) => write!(f, "{n}"),
+            Number::NegInt(n) => write!(f, "{n}"),
+            // Switch to an exponent where plain notation would spell out
+            // long runs of zeros, at the same bounds as JavaScript.
+            Number::Float(n) if *n != 0.0 && !(1e-6..1e21).contains(&n.abs()) => {
+                write!(f, "{n:e}")
+            }
+            Number::Float(n) => {
+                let s = n.to_string();
+                // Keep integral floats recognizable as floats when read back.
+                if s.bytes().all(|b| b == b'-' || b.is_ascii_digit()) {
+                    write!(f, "{s}.0")
+                } else {
+                    write!(f, "{s}")
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_lexeme() {
+        assert_eq!(Number::from_lexeme("0"), Some(Number::PosInt(0)));
+        assert_eq!(
+            Number::from_lexeme("18446744073709551615"),
+            Some(Number::PosInt(u64::MAX))
+        );
+        assert_eq!(
+            Number::from_lexeme("-9223372036854775808"),
+            Some(Number::NegInt(i64::MIN))
+        );
+        assert_eq!(Number::from_lexeme("-0"), Some(Number::Float(-0.0)));
+        assert_eq!(Number::from_lexeme("1.5"), Some(Number::Float(1.5)));
+        assert_eq!(Number::from_lexeme("1e2"), Some(Number::Float(100.0)));
+        assert_eq!(
+            Number::from_lexeme("18446744073709551616"),
+            Some(Number::Float(18446744073709551616.0))
+        );
+        assert_eq!(Number::from_lexeme("1e400"), None);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            Number::PosInt(9007199254740993).to_string(),
+            "9007199254740993"
+        );
+        assert_eq!(Number::NegInt(-42).to_string(), "-42");
+        assert_eq!(Number::Float(10.1234).to_string(), "10.1234");
+        assert_eq!(Number::Float(100.0).to_string(), "100.0");
+        assert_eq!(Number::Float(-0.0).to_string(), "-0.0");
+        assert_eq!(Number::Float(1e20).to_string(), "100000000000000000000.0");
+        assert_eq!(Number::Float(1e21).to_string(), "1e21");
+        assert_eq!(Number::Float(1e-7).to_string(), "1e-7");
+        assert_eq!(Number::Float(0.000001).to_string(), "0.000001");
+    }
+
+    #[test]
+    fn display_round_trips_at_the_extremes() {
+        for n in [
+            5e-324,
+            -1e-300,
+            f64::MIN_POSITIVE,
+            1e300,
+            f64::MAX,
+            f64::MIN,
+            1.5e-10,
+        ] {
+            let s = Number::Float(n).to_string();
+            assert!(s.len() <= 24, "{s}");
+            assert_eq!(Number::from_lexeme(&s), Some(Number::Float(n)));
+        }
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Number::from(-1i64), Number::NegInt(-1));
+        assert_eq!(Number::from(1i64), Number::PosInt(1));
+        assert_eq!(Number::PosInt(u64::MAX).as_i64(), None);
+        assert_eq!(Number::NegInt(-3).as_u64(), None);
+        assert_eq!(Number::NegInt(-3).as_f64(), -3.0);
+        assert!(Number::Float(1.0).is_f64());
+        assert!(!Number::Float(1.0).is_i64());
+    }
+
+    #[test]
+    fn lexeme_conversions() {
+        let n = Number::Lexeme("0.1000000000000000055511151231257827".to_string());
+        assert_eq!(n.to_string(), "0.1000000000000000055511151231257827");
+        assert_eq!(n.as_f64(), 0.1);
+        assert_eq!(n.as_i128(), None);
+        assert_eq!(
+            n.as_decimal(),
+            Some((1000000000000000055511151231257827, -34))
+        );
+        assert!(n.is_f64());
+
+        let n = Number::Lexeme("-170141183460469231731687303715884105728".to_string());
+        assert_eq!(n.as_i128(), Some(i128::MIN));
+        assert_eq!(n.as_i64(), None);
+        assert!(n.is_f64());
+
+        let n = Number::Lexeme("42".to_string());
+        assert_eq!(n.as_u64(), Some(42));
+        assert!(!n.is_f64());
+    }
+
+    #[test]
+    fn as_decimal() {
+        assert_eq!(Number::PosInt(1200).as_decimal(), Some((1200, 0)));
+        assert_eq!(Number::Float(-1.25).as_decimal(), Some((-125, -2)));
+        assert_eq!(
+            Number::Lexeme("1.50E+3".to_string()).as_decimal(),
+            Some((15, 2))
+        );
+        assert_eq!(
+            Number::Lexeme("-0.0".to_string()).as_decimal(),
+            Some((0, 0))
+        );
+    }
+}
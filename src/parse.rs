@@ -1,283 +1,659 @@
+use std::{borrow::Cow, hash::Hash, io};
+
 use indexmap::IndexMap;
 
-use crate::Value;
+use crate::encoding;
+use crate::read::Read;
+use crate::reader::{Event, Reader};
+use crate::{BorrowedValue, Value};
 
-type ValueAndRest<'a> = (Value, &'a str);
 pub type Result<T> = std::result::Result<T, self::Error>;
 
-#[derive(Debug, PartialEq)]
+/// Maximum number of characters kept in [`Position::excerpt`].
+pub(crate) const EXCERPT_LEN: usize = 16;
+
+/// Location of an error in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    /// Byte offset from the start of the input.
+    pub offset: usize,
+    /// Line number, starting at 1.
+    pub line: usize,
+    /// Column counted in UTF-8 bytes, starting at 1.
+    pub column: usize,
+    /// Column counted in UTF-16 code units, starting at 1.
+    pub column_utf16: usize,
+    /// The input following `offset`, cut to a few characters.
+    pub excerpt: String,
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Error {
-    UnexpectedToken(String),
-    MissingExpectedChar(char, String),
-    UnterminatedString,
-    InvalidEscapeSequence(String),
-    InvalidUnicodeEscape,
-    InvalidNumberFormat(String),
-    TrailingCharacters(String),
+    UnexpectedToken(Position),
+    MissingExpectedChar(char, Position),
+    UnterminatedString(Position),
+    InvalidEscapeSequence(String, Position),
+    InvalidUnicodeEscape(Position),
+    InvalidNumberFormat(String, Position),
+    TrailingCharacters(Position),
+    InvalidUtf8(Position),
+    /// Input detected as UTF-16 or UTF-32, named here, is not valid in that
+    /// encoding.
+    InvalidEncoding(String, Position),
+    Io(String, Position),
+    LoneSurrogate(Position),
+    DepthLimitExceeded(Position),
+    InputTooLarge(Position),
+    StringTooLong(Position),
+    TooManyMembers(Position),
+    DuplicateKey(Position),
+    UnterminatedComment(Position),
+}
+
+impl Error {
+    pub fn position(&self) -> &Position {
+        match self {
+            Error::UnexpectedToken(p)
+            | Error::MissingExpectedChar(_, p)
+            | Error::UnterminatedString(p)
+            | Error::InvalidEscapeSequence(_, p)
+            | Error::InvalidUnicodeEscape(p)
+            | Error::InvalidNumberFormat(_, p)
+            | Error::TrailingCharacters(p)
+            | Error::InvalidUtf8(p)
+            | Error::InvalidEncoding(_, p)
+            | Error::Io(_, p)
+            | Error::LoneSurrogate(p)
+            | Error::DepthLimitExceeded(p)
+            | Error::InputTooLarge(p)
+            | Error::StringTooLong(p)
+            | Error::TooManyMembers(p)
+            | Error::DuplicateKey(p)
+            | Error::UnterminatedComment(p) => p,
+        }
+    }
+
+    pub(crate) fn position_mut(&mut self) -> &mut Position {
+        match self {
+            Error::UnexpectedToken(p)
+            | Error::MissingExpectedChar(_, p)
+            | Error::UnterminatedString(p)
+            | Error::InvalidEscapeSequence(_, p)
+            | Error::InvalidUnicodeEscape(p)
+            | Error::InvalidNumberFormat(_, p)
+            | Error::TrailingCharacters(p)
+            | Error::InvalidUtf8(p)
+            | Error::InvalidEncoding(_, p)
+            | Error::Io(_, p)
+            | Error::LoneSurrogate(p)
+            | Error::DepthLimitExceeded(p)
+            | Error::InputTooLarge(p)
+            | Error::StringTooLong(p)
+            | Error::TooManyMembers(p)
+            | Error::DuplicateKey(p)
+            | Error::UnterminatedComment(p) => p,
+        }
+    }
 }
 
 impl std::error::Error for Error {}
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Error::UnexpectedToken(s) => write!(f, "Unexpected token: '{}'", s),
-            Error::MissingExpectedChar(c, s) => {
-                write!(f, "Missing expected character '{}'. Found: '{}'", c, s)
+            Error::UnexpectedToken(p) => {
+                write!(f, "Unexpected token at {}: '{}'", p, p.excerpt)
+            }
+            Error::MissingExpectedChar(c, p) => {
+                write!(
+                    f,
+                    "Missing expected character '{}' at {}. Found: '{}'",
+                    c, p, p.excerpt
+                )
+            }
+            Error::UnterminatedString(p) => write!(f, "Unterminated string at {}", p),
+            Error::InvalidEscapeSequence(s, p) => {
+                write!(f, "Invalid escape sequence at {}: '{}'", p, s)
+            }
+            Error::InvalidUnicodeEscape(p) => {
+                write!(f, "Invalid Unicode escape sequence at {}", p)
+            }
+            Error::InvalidNumberFormat(s, p) => {
+                write!(f, "Invalid number format at {}: '{}'", p, s)
+            }
+            Error::TrailingCharacters(p) => {
+                write!(
+                    f,
+                    "Trailing characters after JSON value at {}: '{}'",
+                    p, p.excerpt
+                )
+            }
+            Error::InvalidUtf8(p) => write!(f, "Invalid UTF-8 sequence at {}", p),
+            Error::InvalidEncoding(e, p) => write!(f, "Invalid {} sequence at {}", e, p),
+            Error::Io(s, p) => write!(f, "I/O error at {}: {}", p, s),
+            Error::LoneSurrogate(p) => {
+                write!(f, "Unpaired UTF-16 surrogate escape at {}", p)
+            }
+            Error::DepthLimitExceeded(p) => {
+                write!(f, "Maximum nesting depth exceeded at {}", p)
             }
-            Error::UnterminatedString => write!(f, "Unterminated string"),
-            Error::InvalidEscapeSequence(s) => write!(f, "Invalid escape sequence: '{}'", s),
-            Error::InvalidUnicodeEscape => write!(f, "Invalid Unicode escape sequence"),
-            Error::InvalidNumberFormat(s) => write!(f, "Invalid number format: '{}'", s),
-            Error::TrailingCharacters(s) => {
-                write!(f, "Trailing characters after JSON value: '{}'", s)
+            Error::InputTooLarge(p) => write!(f, "Maximum input size exceeded at {}", p),
+            Error::StringTooLong(p) => write!(f, "Maximum string length exceeded at {}", p),
+            Error::TooManyMembers(p) => {
+                write!(f, "Maximum number of object members exceeded at {}", p)
             }
+            Error::DuplicateKey(p) => {
+                write!(f, "Duplicate object key at {}: '{}'", p, p.excerpt)
+            }
+            Error::UnterminatedComment(p) => write!(f, "Unterminated comment at {}", p),
         }
     }
 }
 
-pub(crate) fn parse(input: &str) -> Result<Value> {
-    let (v, rest) = value(input)?;
-    let rest = eat_whitespace(rest);
-    if !rest.is_empty() {
-        return Err(Error::TrailingCharacters(rest.to_string()));
+/// Settings for [`parse_with`](crate::parse_with) and
+/// [`Reader::with_options`].
+///
+/// ```
+/// use rj::ParseOptions;
+///
+/// let options = ParseOptions::new().max_depth(32).max_input_size(1 << 20);
+/// assert!(rj::parse_with("[[1]]", &options).is_ok());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    pub(crate) preserve_number_lexemes: bool,
+    pub(crate) lone_surrogates: LoneSurrogates,
+    pub(crate) max_depth: usize,
+    pub(crate) max_input_size: usize,
+    pub(crate) max_string_len: usize,
+    pub(crate) max_members: usize,
+    pub(crate) duplicate_keys: DuplicateKeys,
+    pub(crate) allow_comments: bool,
+    pub(crate) allow_trailing_commas: bool,
+    pub(crate) json5_syntax: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            preserve_number_lexemes: false,
+            lone_surrogates: LoneSurrogates::default(),
+            max_depth: 128,
+            max_input_size: usize::MAX,
+            max_string_len: usize::MAX,
+            max_members: usize::MAX,
+            duplicate_keys: DuplicateKeys::default(),
+            allow_comments: false,
+            allow_trailing_commas: false,
+            json5_syntax: false,
+        }
     }
-    Ok(v)
 }
 
-fn value(input: &str) -> Result<ValueAndRest> {
-    let input = eat_whitespace(input);
+/// What to do when an object has several members with the same key.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DuplicateKeys {
+    /// Fail with [`Error::DuplicateKey`] pointing at the second occurrence.
+    Reject,
+    /// Keep the first value and ignore later ones.
+    FirstWins,
+    /// Keep the last value, at the position where the key first appeared.
+    #[default]
+    LastWins,
+    /// Keep every value, in order, in an array.
+    Collect,
+}
+
+/// What to do with a `\uXXXX` escape of a UTF-16 surrogate that is not part
+/// of a high/low pair.
+///
+/// There is no policy that preserves lone surrogates. A [`Value::String`] is
+/// UTF-8 and cannot hold one, and keeping the escape as text would make the
+/// string indistinguishable from input that escaped the backslash. Callers who
+/// need the original code units should parse with `Reject` and fall back to
+/// their own handling of [`Error::LoneSurrogate`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LoneSurrogates {
+    /// Fail with [`Error::LoneSurrogate`].
+    #[default]
+    Reject,
+    /// Decode as U+FFFD REPLACEMENT CHARACTER.
+    Replace,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// JSON with comments, as used by VS Code settings and `tsconfig.json`:
+    /// `//` and `/* */` comments and trailing commas are allowed.
+    pub fn jsonc() -> Self {
+        Self::default()
+            .allow_comments(true)
+            .allow_trailing_commas(true)
+    }
 
-    if let Some(rest) = input.strip_prefix("false") {
-        return Ok((Value::Boolean(false), rest));
+    /// [JSON5](https://spec.json5.org/): JSONC plus unquoted member names,
+    /// single-quoted strings, more escape sequences and number forms.
+    pub fn json5() -> Self {
+        Self::jsonc().json5_syntax(true)
     }
-    if let Some(rest) = input.strip_prefix("null") {
-        return Ok((Value::Null, rest));
+
+    /// Keeps every number as the text it was written as, in
+    /// [`Number::Lexeme`](crate::Number::Lexeme), so it is written back with
+    /// the same digits.
+    pub fn preserve_number_lexemes(mut self, yes: bool) -> Self {
+        self.preserve_number_lexemes = yes;
+        self
+    }
+
+    /// These options as the `format` functions use them: numbers are kept as
+    /// they were written, so pretty-printing does not change their digits.
+    pub fn for_formatting(self) -> Self {
+        self.preserve_number_lexemes(true)
+    }
+
+    pub fn lone_surrogates(mut self, policy: LoneSurrogates) -> Self {
+        self.lone_surrogates = policy;
+        self
+    }
+
+    /// Maximum number of arrays and objects a value may be nested in.
+    /// Defaults to 128, which is also the limit for [`parse`](fn@crate::parse).
+    ///
+    /// Parsing itself does not recurse, so this may be raised as far as memory
+    /// allows, but dropping and writing a [`Value`] do recurse.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Maximum number of bytes to read. Unlimited by default.
+    pub fn max_input_size(mut self, bytes: usize) -> Self {
+        self.max_input_size = bytes;
+        self
+    }
+
+    /// Maximum length in bytes of a string or object key after unescaping.
+    /// Unlimited by default.
+    pub fn max_string_len(mut self, bytes: usize) -> Self {
+        self.max_string_len = bytes;
+        self
     }
-    if let Some(rest) = input.strip_prefix("true") {
-        return Ok((Value::Boolean(true), rest));
+
+    /// Maximum number of members in a single object. Unlimited by default.
+    pub fn max_members(mut self, members: usize) -> Self {
+        self.max_members = members;
+        self
     }
-    if input.starts_with('{') {
-        let (v, rest) = object(input)?;
-        return Ok((v, rest));
+
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicate_keys = policy;
+        self
     }
-    if input.starts_with('[') {
-        let (v, rest) = array(input)?;
-        return Ok((v, rest));
+
+    /// Skips `// line` and `/* block */` comments wherever whitespace is
+    /// allowed.
+    pub fn allow_comments(mut self, yes: bool) -> Self {
+        self.allow_comments = yes;
+        self
     }
-    if input.starts_with('"') {
-        let (v, rest) = string(input)?;
-        return Ok((v, rest));
+
+    /// Accepts a comma after the last element of an array or object.
+    pub fn allow_trailing_commas(mut self, yes: bool) -> Self {
+        self.allow_trailing_commas = yes;
+        self
     }
-    if input.starts_with('-') || input.chars().next().is_some_and(|c| c.is_ascii_digit()) {
-        let (v, rest) = number(input)?;
-        return Ok((v, rest));
+
+    /// Accepts the JSON5 forms of member names, strings, numbers and
+    /// whitespace. Unquoted member names must start with an ASCII letter, `_`
+    /// or `$`.
+    pub fn json5_syntax(mut self, yes: bool) -> Self {
+        self.json5_syntax = yes;
+        self
     }
+}
 
-    Err(Error::UnexpectedToken(input.to_string()))
+pub(crate) fn parse(input: &str) -> Result<Value> {
+    build(Reader::new(input))
 }
 
-/// whitespace = \x20 \x09 \x0a \x0d
-/// - \x20 space
-/// - \x09 horizontal tab
-/// - \x0a line feed or new line
-/// - \x0d carriage return
-fn is_whitespace(c: char) -> bool {
-    c == '\x20' || c == '\x09' || c == '\x0a' || c == '\x0d'
+pub(crate) fn parse_with(input: &str, options: &ParseOptions) -> Result<Value> {
+    build(Reader::new(input).with_options(options.clone()))
 }
 
-fn eat_whitespace(input: &str) -> &str {
-    let mut pos = 0;
-    for c in input.chars() {
-        if !is_whitespace(c) {
-            break;
-        }
-        pos += c.len_utf8(); // Advance by byte length of char
-    }
-    &input[pos..]
+pub(crate) fn parse_bytes(input: &[u8], options: &ParseOptions) -> Result<Value> {
+    let decoded = encoding::decode(input)?;
+    parse_with(&decoded.text, options).map_err(|mut e| {
+        let p = e.position_mut();
+        p.offset = decoded.input_offset(p.offset);
+        e
+    })
 }
 
-fn expect(input: &str, expected_char: char) -> Result<&str> {
-    eat_whitespace(input)
-        .strip_prefix(expected_char)
-        .ok_or_else(|| Error::MissingExpectedChar(expected_char, input.to_string()))
+pub(crate) fn parse_borrowed(input: &str) -> Result<BorrowedValue<'_>> {
+    build(Reader::new(input))
 }
 
-fn object(input: &str) -> Result<ValueAndRest> {
-    let mut cur_input = expect(input, '{')?;
+pub(crate) fn parse_reader<R: io::Read>(reader: R) -> Result<Value> {
+    build(Reader::from_reader(reader))
+}
 
-    if let Some(rest) = eat_whitespace(cur_input).strip_prefix('}') {
-        return Ok((Value::Object(IndexMap::new()), rest));
-    }
+pub(crate) fn parse_reader_with<R: io::Read>(reader: R, options: &ParseOptions) -> Result<Value> {
+    build(Reader::from_reader(reader).with_options(options.clone()))
+}
 
-    let mut obj: IndexMap<String, Value> = IndexMap::new();
-    loop {
-        // Parse key
-        let (v, rest) = string(eat_whitespace(cur_input))?;
-        let key = match v {
-            Value::String(s) => s,
-            _ => unreachable!("string() should always return Value::String"),
-        };
+pub(crate) fn parse_recovering(input: &str, options: &ParseOptions) -> (Value, Vec<Error>) {
+    build_recovering(Reader::new(input).with_options(options.clone()))
+}
+
+pub(crate) fn parse_reader_recovering<R: io::Read>(
+    reader: R,
+    options: &ParseOptions,
+) -> (Value, Vec<Error>) {
+    build_recovering(Reader::from_reader(reader).with_options(options.clone()))
+}
+
+/// A step on the way from the root of a document to one of its values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// A document read from a prefix of its text by
+/// [`parse_partial`](crate::parse_partial).
+#[derive(Debug, PartialEq)]
+pub struct Partial {
+    pub value: Value,
+    /// Path to the value the input ended in, or `None` if the document was
+    /// complete.
+    ///
+    /// The value at the path is missing if it was a member whose value had not
+    /// started, or a token other than a string. Otherwise it holds what had
+    /// been read.
+    pub incomplete: Option<Vec<PathSegment>>,
+}
 
-        cur_input = expect(rest, ':')?;
+pub(crate) fn parse_partial(input: &str, options: &ParseOptions) -> Result<Partial> {
+    let mut reader = Reader::new(input).with_options(options.clone()).partial();
+    let (value, incomplete) = build_inner(&mut reader, None)?;
+    Ok(Partial { value, incomplete })
+}
 
-        // Parse value
-        let (v, rest) = value(cur_input)?;
-        obj.insert(key, v);
+/// Iterates over a sequence of JSON documents in the same input.
+///
+/// Documents may be concatenated, separated by whitespace, or each start with
+/// a record separator (U+001E) as in an RFC 7464 JSON text sequence. After an
+/// error, reading goes on at the next record separator if there is one.
+pub struct Documents<'de, R> {
+    reader: Reader<'de, R>,
+    /// Where the document last returned starts.
+    offset: usize,
+    done: bool,
+}
 
-        if let Some(rest) = eat_whitespace(rest).strip_prefix(',') {
-            cur_input = rest;
-        } else if let Some(rest) = eat_whitespace(rest).strip_prefix('}') {
-            cur_input = rest;
-            break;
-        } else {
-            return Err(Error::UnexpectedToken(rest.to_string()));
+impl<'de, R: Read<'de>> Documents<'de, R> {
+    pub(crate) fn new(reader: Reader<'de, R>) -> Self {
+        Self {
+            reader: reader.multiple(),
+            offset: 0,
+            done: false,
         }
     }
 
-    Ok((Value::Object(obj), cur_input))
-}
+    pub fn with_options(self, options: ParseOptions) -> Self {
+        Self {
+            reader: self.reader.with_options(options),
+            ..self
+        }
+    }
 
-fn array(input: &str) -> Result<ValueAndRest> {
-    let mut cur_input = expect(input, '[')?;
+    /// Byte offset at which the document last returned starts.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
 
-    if let Some(rest) = eat_whitespace(cur_input).strip_prefix(']') {
-        return Ok((Value::Array(Vec::new()), rest));
+    fn next_document(&mut self) -> Result<Option<Value>> {
+        if self.reader.at_end()? {
+            return Ok(None);
+        }
+        self.offset = self.reader.mark().offset();
+        build_inner(&mut self.reader, None).map(|(v, _)| Some(v))
     }
+}
 
-    let mut values: Vec<Value> = Vec::new();
-    let (v, rest) = value(cur_input)?;
-    values.push(v);
-    cur_input = rest;
+impl<'de, R: Read<'de>> Iterator for Documents<'de, R> {
+    type Item = Result<Value>;
 
-    while let Some(rest) = eat_whitespace(cur_input).strip_prefix(',') {
-        let (v, rest) = value(rest)?;
-        values.push(v);
-        cur_input = rest;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.next_document();
+        if res.is_err() {
+            self.done = !matches!(self.reader.skip_to_record(), Ok(true));
+        }
+        res.transpose()
     }
+}
 
-    cur_input = expect(cur_input, ']')?;
+/// A document type that [`build`] can assemble from reader events.
+pub(crate) trait Build<'de>: Sized {
+    type Key: Hash + Eq + AsRef<str>;
 
-    Ok((Value::Array(values), cur_input))
+    fn key(k: Cow<'de, str>) -> Self::Key;
+    /// Converts a scalar event. Never called with container or key events.
+    fn scalar(event: Event<'de>) -> Self;
+    fn object(obj: IndexMap<Self::Key, Self>) -> Self;
+    fn array(arr: Vec<Self>) -> Self;
+    fn as_array_mut(&mut self) -> Option<&mut Vec<Self>>;
 }
 
-fn string(input: &str) -> Result<ValueAndRest> {
-    let cur_input = expect(input, '"')?;
+/// A container whose members are still being read.
+enum Frame<'de, V: Build<'de>> {
+    Object {
+        members: IndexMap<V::Key, V>,
+        key: Option<V::Key>,
+        /// Members turned into arrays by [`DuplicateKeys::Collect`].
+        collected: Vec<usize>,
+    },
+    Array(Vec<V>),
+}
 
-    if let Some(rest) = eat_whitespace(cur_input).strip_prefix('"') {
-        return Ok((Value::String(String::new()), rest));
-    }
+/// Builds a document from the events of `reader`.
+///
+/// Open containers are kept in a `Vec` rather than on the call stack, so the
+/// nesting depth is only limited by memory.
+fn build<'de, R: Read<'de>, V: Build<'de>>(mut reader: Reader<'de, R>) -> Result<V> {
+    build_inner(&mut reader, None).map(|(v, _)| v)
+}
 
-    let mut chars = cur_input.char_indices();
-    let mut parsed_string = String::new();
+/// Builds as much of a document as possible, collecting errors instead of
+/// stopping at the first.
+fn build_recovering<'de, R: Read<'de>, V: Build<'de>>(
+    mut reader: Reader<'de, R>,
+) -> (V, Vec<Error>) {
+    let mut errors = Vec::new();
+    let (v, _) = build_inner(&mut reader, Some(&mut errors)).expect("errors are collected");
+    (v, errors)
+}
 
+/// Builds a document, returning the first error unless `errors` is given to
+/// collect them in, and the path a partial reader's input ended in.
+fn build_inner<'de, R: Read<'de>, V: Build<'de>>(
+    reader: &mut Reader<'de, R>,
+    mut errors: Option<&mut Vec<Error>>,
+) -> Result<(V, Option<Vec<PathSegment>>)> {
+    let duplicate_keys = reader.options().duplicate_keys;
+    let mut stack: Vec<Frame<V>> = Vec::new();
+    let mut incomplete = None;
     loop {
-        let Some((idx, c)) = chars.next() else {
-            return Err(Error::UnterminatedString);
+        let next = reader.next_event();
+        if let (None, Some(start)) = (&incomplete, reader.truncated_at()) {
+            let cut = start.offset() < reader.mark().offset();
+            incomplete = Some(path(&stack, cut));
+        }
+        let event = match next {
+            Ok(Some(event)) => event,
+            // Only after an error in, or the end of the input during, a
+            // top-level scalar.
+            Ok(None) => return Ok((V::scalar(Event::Null), incomplete)),
+            Err(e) => {
+                let Some(errors) = errors.as_deref_mut() else {
+                    return Err(e);
+                };
+                recover(reader, errors, e);
+                // A member whose value was lost reads as `null`.
+                if let Some(Frame::Object {
+                    members,
+                    key: key @ Some(_),
+                    collected,
+                }) = stack.last_mut()
+                {
+                    let key = key.take().unwrap();
+                    insert_member(
+                        members,
+                        collected,
+                        key,
+                        V::scalar(Event::Null),
+                        duplicate_keys,
+                    );
+                }
+                continue;
+            }
         };
-
-        // Calculate the byte position in the original `input` string
-        let current_byte_pos_relative_to_original_input =
-            input.len() - cur_input.len() + idx + c.len_utf8();
-
-        match c {
-            '"' => {
-                return Ok((
-                    Value::String(parsed_string),
-                    &input[current_byte_pos_relative_to_original_input..],
-                ));
+        let v = match event {
+            Event::StartObject => {
+                stack.push(Frame::Object {
+                    members: IndexMap::new(),
+                    key: None,
+                    collected: Vec::new(),
+                });
+                continue;
             }
-            '\\' => {
-                let Some((_, escaped_char)) = chars.next() else {
-                    return Err(Error::InvalidEscapeSequence("\\".to_string()));
-                };
-
-                match escaped_char {
-                    '"' => parsed_string.push('"'),    // quotation mark
-                    '\\' => parsed_string.push('\\'),  // reverse solidus
-                    '/' => parsed_string.push('/'),    // solidus
-                    'b' => parsed_string.push('\x08'), // backspace
-                    'f' => parsed_string.push('\x0C'), // form feed
-                    'n' => parsed_string.push('\n'),   // line feed
-                    'r' => parsed_string.push('\r'),   // carriage return
-                    't' => parsed_string.push('\t'),   // tab
-                    'u' => {
-                        let mut hex_val: u32 = 0;
-                        for _ in 0..4 {
-                            match chars.next() {
-                                Some((_, c)) => {
-                                    let digit =
-                                        c.to_digit(16).ok_or(Error::InvalidUnicodeEscape)?;
-                                    hex_val = (hex_val << 4) | digit;
-                                }
-                                None => {
-                                    return Err(Error::InvalidUnicodeEscape);
-                                }
-                            }
-                        }
-
-                        let unicode_char =
-                            char::from_u32(hex_val).ok_or(Error::InvalidUnicodeEscape)?;
-                        parsed_string.push(unicode_char);
-                    }
-                    _ => {
-                        return Err(Error::InvalidEscapeSequence(format!("\\{}", escaped_char)));
+            Event::StartArray => {
+                stack.push(Frame::Array(Vec::new()));
+                continue;
+            }
+            Event::Key(k) => {
+                if let Some(Frame::Object { members, key, .. }) = stack.last_mut() {
+                    let k = V::key(k);
+                    if duplicate_keys == DuplicateKeys::Reject && members.contains_key(&k) {
+                        let e = Error::DuplicateKey(reader.event_position());
+                        let Some(errors) = errors.as_deref_mut() else {
+                            return Err(e);
+                        };
+                        errors.push(e);
                     }
+                    *key = Some(k);
                 }
+                continue;
             }
-            _ if c == '\n' || c == '\r' || c == '\t' => {
-                return Err(Error::UnexpectedToken(c.to_string()));
+            Event::EndObject | Event::EndArray => match stack.pop() {
+                Some(Frame::Object { members, .. }) => V::object(members),
+                Some(Frame::Array(arr)) => V::array(arr),
+                None => unreachable!("the reader balances containers"),
+            },
+            scalar => V::scalar(scalar),
+        };
+
+        match stack.last_mut() {
+            Some(Frame::Object {
+                members,
+                key,
+                collected,
+            }) => {
+                let key = key
+                    .take()
+                    .expect("the reader emits a key before each member");
+                insert_member(members, collected, key, v, duplicate_keys);
             }
-            _ => {
-                parsed_string.push(c);
+            Some(Frame::Array(arr)) => arr.push(v),
+            None => {
+                // Fails if anything but whitespace follows the value.
+                if let Err(e) = reader.next_event() {
+                    let Some(errors) = errors else {
+                        return Err(e);
+                    };
+                    recover(reader, errors, e);
+                }
+                return Ok((v, incomplete));
             }
         }
     }
 }
 
-fn number(input: &str) -> Result<ValueAndRest> {
-    let mut cur_input = eat_whitespace(input);
-
-    let mut minus = false;
-    if let Some(rest) = cur_input.strip_prefix('-') {
-        minus = true;
-        cur_input = rest;
+/// Path to the value being read in the innermost container of `stack`, or to
+/// that container if no member name or, with `cut`, other token of the value
+/// had been read.
+fn path<'de, V: Build<'de>>(stack: &[Frame<'de, V>], cut: bool) -> Vec<PathSegment> {
+    let mut path = Vec::new();
+    for (i, frame) in stack.iter().enumerate() {
+        let innermost = i + 1 == stack.len();
+        match frame {
+            Frame::Object { key: Some(k), .. } => {
+                path.push(PathSegment::Key(k.as_ref().to_string()))
+            }
+            Frame::Array(arr) if cut || !innermost => path.push(PathSegment::Index(arr.len())),
+            _ => {}
+        }
     }
+    path
+}
 
-    let mut buf = String::new();
-    let mut enable_sign = false;
-    for c in cur_input.chars() {
-        match c {
-            '0'..='9' => buf.push(c),
-            '.' => buf.push(c),
-            'e' | 'E' => {
-                enable_sign = true;
-                buf.push(c);
-            }
-            '-' | '+' => {
-                if enable_sign {
-                    buf.push(c);
-                    enable_sign = false;
-                } else {
-                    return Err(Error::InvalidNumberFormat(
-                        "sign only allowed at the beginning of the number or immediately after 'e' or 'E' for exponents".to_string(),
-                    ));
-                }
-            }
-            _ => break, // the char is not part of number.
+/// Records `e` and skips ahead to where reading can go on.
+fn recover<'de, R: Read<'de>>(reader: &mut Reader<'de, R>, errors: &mut Vec<Error>, e: Error) {
+    errors.push(e);
+    if let Some(fatal) = reader.recover() {
+        // A failing source fails again at the same place while skipping.
+        if errors.last() != Some(&fatal) {
+            errors.push(fatal);
         }
     }
+}
 
-    cur_input = cur_input.strip_prefix(&buf).unwrap();
-    if minus {
-        Ok((Value::Number(buf.parse::<f64>().unwrap() * -1.0), cur_input))
-    } else {
-        Ok((Value::Number(buf.parse::<f64>().unwrap()), cur_input))
+fn insert_member<'de, V: Build<'de>>(
+    members: &mut IndexMap<V::Key, V>,
+    collected: &mut Vec<usize>,
+    key: V::Key,
+    v: V,
+    policy: DuplicateKeys,
+) {
+    let Some(i) = members.get_index_of(&key) else {
+        members.insert(key, v);
+        return;
+    };
+    let existing = &mut members[i];
+    match policy {
+        // Rejected keys are only inserted when recovering from errors.
+        DuplicateKeys::Reject | DuplicateKeys::FirstWins => {}
+        DuplicateKeys::LastWins => *existing = v,
+        DuplicateKeys::Collect => {
+            if collected.contains(&i) {
+                existing
+                    .as_array_mut()
+                    .expect("collected members are arrays")
+                    .push(v);
+            } else {
+                let first = std::mem::replace(existing, V::array(Vec::new()));
+                *existing = V::array(vec![first, v]);
+                collected.push(i);
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Number;
     use indexmap::IndexMap;
 
     #[test]
@@ -332,28 +708,41 @@ mod tests {
     fn parse_unterminated_string() {
         let json = r#""hello"#;
         let err = parse(json).unwrap_err();
-        assert_eq!(err, Error::UnterminatedString);
+        assert!(matches!(err, Error::UnterminatedString(ref p) if p.offset == 0));
     }
 
     #[test]
     fn parse_string_with_invalid_escape() {
         let json = r#""hello\x""#;
         let err = parse(json).unwrap_err();
-        assert_eq!(err, Error::InvalidEscapeSequence("\\x".to_string()));
+        assert!(
+            matches!(err, Error::InvalidEscapeSequence(ref s, ref p) if s == "\\x" && p.offset == 6)
+        );
+    }
+
+    #[test]
+    fn parse_string_with_control_characters() {
+        for json in ["\"a\u{0}b\"", "\"a\u{1f}b\""] {
+            let err = parse(json).unwrap_err();
+            assert!(matches!(err, Error::UnexpectedToken(ref p) if p.offset == 2));
+            let options = ParseOptions::json5();
+            assert!(matches!(parse_with(json, &options), Ok(Value::String(_))));
+        }
+        assert!(parse_with("\"a\nb\"", &ParseOptions::json5()).is_err());
     }
 
     #[test]
     fn parse_string_with_incomplete_unicode_escape() {
         let json = r#""\u123""#;
         let err = parse(json).unwrap_err();
-        assert_eq!(err, Error::InvalidUnicodeEscape);
+        assert!(matches!(err, Error::InvalidUnicodeEscape(ref p) if p.offset == 1));
     }
 
     #[test]
     fn parse_string_with_invalid_unicode_hex() {
         let json = r#""\u123G""#;
         let err = parse(json).unwrap_err();
-        assert_eq!(err, Error::InvalidUnicodeEscape);
+        assert!(matches!(err, Error::InvalidUnicodeEscape(ref p) if p.offset == 1));
     }
 
     #[test]
@@ -415,32 +804,27 @@ mod tests {
     fn parse_extra_characters_after_value() {
         let json = r#"{}extra"#;
         let err = parse(json).unwrap_err();
-        assert_eq!(
-            err,
-            Error::TrailingCharacters("extra".to_string())
-        );
+        assert!(matches!(err, Error::TrailingCharacters(_)));
+        assert_eq!(err.position().offset, 2);
+        assert_eq!(err.position().excerpt, "extra");
     }
 
     #[test]
     fn parse_object_missing_colon() {
         let json = r#"{"key" "value"}"#;
         let err = parse(json).unwrap_err();
-        assert_eq!(
-            err,
-            Error::MissingExpectedChar(':', " \"value\"}".to_string())
-        );
+        assert!(matches!(err, Error::MissingExpectedChar(':', _)));
+        assert_eq!(err.position().offset, 7);
+        assert_eq!(err.position().excerpt, "\"value\"}");
     }
 
     #[test]
     fn parse_object_missing_comma_or_brace() {
         let json = r#"{"key": "value" "another_key": "another_value"}"#;
         let err = parse(json).unwrap_err();
-        assert_eq!(
-            err,
-            Error::UnexpectedToken(
-                " \"another_key\": \"another_value\"}".to_string()
-            )
-        );
+        assert!(matches!(err, Error::UnexpectedToken(_)));
+        assert_eq!(err.position().offset, 16);
+        assert_eq!(err.position().excerpt, "\"another_key\": \"");
     }
 
     #[test]
@@ -449,7 +833,7 @@ mod tests {
         let parsed = parse(json).unwrap();
         match parsed {
             Value::Number(n) => {
-                assert_eq!(n, 10.0)
+                assert_eq!(n, Number::PosInt(10))
             }
             _ => panic!("Expected a number, got {:?}", parsed),
         }
@@ -461,7 +845,7 @@ mod tests {
         let parsed = parse(json).unwrap();
         match parsed {
             Value::Number(n) => {
-                assert_eq!(n, -10.0)
+                assert_eq!(n, Number::NegInt(-10))
             }
             _ => panic!("Expected a number, got {:?}", parsed),
         }
@@ -473,7 +857,7 @@ mod tests {
         let parsed = parse(json).unwrap();
         match parsed {
             Value::Number(n) => {
-                assert_eq!(n, 10.01234)
+                assert_eq!(n, Number::Float(10.01234))
             }
             _ => panic!("Expected a number, got {:?}", parsed),
         }
@@ -485,7 +869,7 @@ mod tests {
         let parsed = parse(json).unwrap();
         match parsed {
             Value::Number(n) => {
-                assert_eq!(n, 10000.0)
+                assert_eq!(n, Number::Float(10000.0))
             }
             _ => panic!("Expected a number, got {:?}", parsed),
         }
@@ -497,7 +881,7 @@ mod tests {
         let parsed = parse(json).unwrap();
         match parsed {
             Value::Number(n) => {
-                assert_eq!(n, 0.01)
+                assert_eq!(n, Number::Float(0.01))
             }
             _ => panic!("Expected a number, got {:?}", parsed),
         }
@@ -509,7 +893,7 @@ mod tests {
         let parsed = parse(json).unwrap();
         match parsed {
             Value::Number(n) => {
-                assert_eq!(n, 10000.0)
+                assert_eq!(n, Number::Float(10000.0))
             }
             _ => panic!("Expected a number, got {:?}", parsed),
         }
@@ -613,15 +997,15 @@ mod tests {
 }
 "#;
         let v = parse(json).unwrap();
-        assert_eq!(v["Image"]["Width"], "800.0".into());
-        assert_eq!(v["Image"]["Height"], "600.0".into());
+        assert_eq!(v["Image"]["Width"], "800".into());
+        assert_eq!(v["Image"]["Height"], "600".into());
         assert_eq!(v["Image"]["Title"], r#""View from 15th Floor""#.into());
         assert_eq!(
             v["Image"]["Thumbnail"]["Url"],
             r#""http://www.example.com/image/481989943""#.into()
         );
-        assert_eq!(v["Image"]["Thumbnail"]["Height"], "125.0".into());
-        assert_eq!(v["Image"]["Thumbnail"]["Width"], "100.0".into());
+        assert_eq!(v["Image"]["Thumbnail"]["Height"], "125".into());
+        assert_eq!(v["Image"]["Thumbnail"]["Width"], "100".into());
         assert_eq!(v["Image"]["Animated"], "false".into());
         assert_eq!(v["Image"]["IDs"], "[116,943,234,38793]".into());
     }
@@ -670,4 +1054,743 @@ mod tests {
         assert_eq!(v[1]["Zip"], r#""94085""#.into());
         assert_eq!(v[1]["Country"], r#""US""#.into());
     }
+
+    #[test]
+    fn error_position_line_and_column() {
+        let json = "{\n  \"a\": 1,\n  \"b\": ?\n}";
+        let p = parse(json).unwrap_err().position().clone();
+        assert_eq!(p.offset, 19);
+        assert_eq!(p.line, 3);
+        assert_eq!(p.column, 8);
+        assert_eq!(p.column_utf16, 8);
+        assert_eq!(p.excerpt, "?\n}");
+    }
+
+    #[test]
+    fn error_position_columns_with_multibyte_chars() {
+        let json = r#"["あ😀", ?]"#;
+        let p = parse(json).unwrap_err().position().clone();
+        assert_eq!(p.offset, 12);
+        assert_eq!(p.line, 1);
+        assert_eq!(p.column, 13);
+        assert_eq!(p.column_utf16, 9);
+    }
+
+    #[test]
+    fn error_excerpt_is_short() {
+        let json = format!("[1, ?{}]", "x".repeat(1000));
+        let err = parse(&json).unwrap_err();
+        assert_eq!(err.position().excerpt.chars().count(), EXCERPT_LEN);
+    }
+
+    /// Hands out its input one byte per `read` call.
+    struct ByteByByte<'a>(&'a [u8]);
+
+    impl io::Read for ByteByByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((b, rest)) if !buf.is_empty() => {
+                    buf[0] = *b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_reader_matches_parse() {
+        let json = r#"{"key": ["あ😀", 1.5, true, null, {"nested": "\u3042"}]}"#;
+        assert_eq!(parse_reader(json.as_bytes()).unwrap(), parse(json).unwrap());
+    }
+
+    #[test]
+    fn parse_reader_with_split_utf8_sequences() {
+        let json = r#"["あ😀é", "\"あ\""]"#;
+        let parsed = parse_reader(ByteByByte(json.as_bytes())).unwrap();
+        assert_eq!(parsed, parse(json).unwrap());
+    }
+
+    #[test]
+    fn parse_reader_larger_than_buffer() {
+        let json = format!("[{}\"end\"]", "\"あいう\",".repeat(4096));
+        let parsed = parse_reader(json.as_bytes()).unwrap();
+        match parsed {
+            Value::Array(arr) => assert_eq!(arr.len(), 4097),
+            _ => panic!("Expected an array, got {:?}", parsed),
+        }
+    }
+
+    #[test]
+    fn parse_reader_error_position() {
+        let json = "[\n  1,\n  ?]";
+        let err = parse_reader(ByteByByte(json.as_bytes())).unwrap_err();
+        assert!(matches!(err, Error::UnexpectedToken(_)));
+        assert_eq!(err.position().line, 3);
+        assert_eq!(err.position().column, 3);
+    }
+
+    #[test]
+    fn parse_reader_invalid_utf8() {
+        let err = parse_reader(&b"[\"ab\xff\"]"[..]).unwrap_err();
+        assert!(matches!(err, Error::InvalidUtf8(ref p) if p.offset == 4));
+
+        let err = parse_reader(&b"\"\xe3\x81\""[..]).unwrap_err();
+        assert!(matches!(err, Error::InvalidUtf8(ref p) if p.offset == 1));
+    }
+
+    #[test]
+    fn parse_bytes_encodings() {
+        let json = "\u{FEFF}{\"a\": [\"\u{e9}\u{1F600}\", 1]}";
+        let expected = parse(&json[3..]).unwrap();
+        assert_eq!(
+            parse_bytes(json.as_bytes(), &ParseOptions::new()).unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_bytes(&json.as_bytes()[3..], &ParseOptions::new()).unwrap(),
+            expected
+        );
+
+        let utf16: Vec<u8> = json.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(parse_bytes(&utf16, &ParseOptions::new()).unwrap(), expected);
+        let utf32: Vec<u8> = json
+            .chars()
+            .flat_map(|c| u32::from(c).to_le_bytes())
+            .collect();
+        assert_eq!(parse_bytes(&utf32, &ParseOptions::new()).unwrap(), expected);
+    }
+
+    #[test]
+    fn parse_bytes_error_offsets() {
+        let err = parse_bytes(b"\xEF\xBB\xBF[\"a\" \"\xFF\"]", &ParseOptions::new()).unwrap_err();
+        assert!(matches!(err, Error::InvalidUtf8(ref p) if p.offset == 9));
+
+        let err = parse_bytes(b"\xEF\xBB\xBF[1 2]", &ParseOptions::new()).unwrap_err();
+        assert!(matches!(err, Error::MissingExpectedChar(']', ref p) if p.offset == 6));
+
+        let utf16: Vec<u8> = "[\"\u{1F600}\", ?]"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let err = parse_bytes(&utf16, &ParseOptions::new()).unwrap_err();
+        assert_eq!(err.position().offset, 14);
+        assert_eq!(err.position().column_utf16, 8);
+    }
+
+    #[test]
+    fn parse_reader_io_error() {
+        struct Failing;
+        impl io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("boom"))
+            }
+        }
+
+        let err = parse_reader(io::Read::chain(&b"[1,"[..], Failing)).unwrap_err();
+        assert!(matches!(err, Error::Io(ref s, ref p) if s == "boom" && p.offset == 3));
+    }
+
+    #[test]
+    fn parse_borrowed_borrows_unescaped_strings() {
+        let json = r#"{"name": "rj", "quote": "\"hi\"", "tags": ["a\u0042", "c"]}"#;
+        let v = parse_borrowed(json).unwrap();
+        assert!(matches!(
+            v["name"],
+            BorrowedValue::String(Cow::Borrowed("rj"))
+        ));
+        assert!(matches!(v["quote"], BorrowedValue::String(Cow::Owned(ref s)) if s == "\"hi\""));
+        assert!(matches!(v["tags"][0], BorrowedValue::String(Cow::Owned(ref s)) if s == "aB"));
+        assert!(matches!(
+            v["tags"][1],
+            BorrowedValue::String(Cow::Borrowed("c"))
+        ));
+        match &v {
+            BorrowedValue::Object(obj) => {
+                assert!(obj.keys().all(|k| matches!(k, Cow::Borrowed(_))))
+            }
+            _ => panic!("Expected an object, got {:?}", v),
+        }
+    }
+
+    #[test]
+    fn parse_borrowed_into_owned_matches_parse() {
+        let json = r#"[{"k\n": [1, true, null, "x"]}, -2.5, {}]"#;
+        let owned = parse_borrowed(json).unwrap().into_owned();
+        assert_eq!(owned, parse(json).unwrap());
+    }
+
+    #[test]
+    fn parse_large_integers_exactly() {
+        let json = "[9007199254740993, -9007199254740993, 18446744073709551615]";
+        let v = parse(json).unwrap();
+        assert_eq!(v[0], Value::Number(Number::PosInt(9007199254740993)));
+        assert_eq!(v[1], Value::Number(Number::NegInt(-9007199254740993)));
+        assert_eq!(v[2], Value::Number(Number::PosInt(u64::MAX)));
+    }
+
+    #[test]
+    fn parse_with_preserved_number_lexemes() {
+        let json = "[0.1000000000000000055511151231257827, 1E+2, -0, 12345678901234567890123]";
+        let options = ParseOptions::new().preserve_number_lexemes(true);
+        let v = parse_with(json, &options).unwrap();
+        assert_eq!(
+            v[0],
+            Value::Number(Number::Lexeme(
+                "0.1000000000000000055511151231257827".to_string()
+            ))
+        );
+        assert_eq!(v[1], Value::Number(Number::Lexeme("1E+2".to_string())));
+        assert_eq!(
+            v.to_string(),
+            "[0.1000000000000000055511151231257827,1E+2,-0,12345678901234567890123]"
+        );
+    }
+
+    #[test]
+    fn parse_number_rfc8259_grammar() {
+        for json in ["0", "-0", "0.5", "-0.0e-0", "1E10", "1e+2", "123.456e-7"] {
+            assert!(parse(json).is_ok(), "{json}");
+        }
+    }
+
+    #[test]
+    fn parse_invalid_numbers() {
+        let cases = [
+            ("-", 1),
+            ("1e", 2),
+            ("1E+", 3),
+            ("1.", 2),
+            ("1..", 2),
+            ("012", 1),
+            ("-01", 2),
+            ("[-]", 2),
+            ("1e400", 0),
+        ];
+        for (json, offset) in cases {
+            match parse(json) {
+                Err(Error::InvalidNumberFormat(_, p)) => assert_eq!(p.offset, offset, "{json}"),
+                other => panic!("{json}: expected InvalidNumberFormat, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_numbers_followed_by_garbage() {
+        assert!(matches!(parse("1.2.3"), Err(Error::TrailingCharacters(ref p)) if p.offset == 3));
+        assert!(
+            matches!(parse("[1-2]"), Err(Error::MissingExpectedChar(']', ref p)) if p.offset == 2)
+        );
+        assert!(matches!(parse(".5"), Err(Error::UnexpectedToken(ref p)) if p.offset == 0));
+        assert!(matches!(parse("+1"), Err(Error::UnexpectedToken(_))));
+    }
+
+    #[test]
+    fn parse_out_of_range_number_as_lexeme() {
+        let options = ParseOptions::new().preserve_number_lexemes(true);
+        let v = parse_with("1e400", &options).unwrap();
+        assert_eq!(v, Value::Number(Number::Lexeme("1e400".to_string())));
+    }
+
+    #[test]
+    fn parse_string_with_surrogate_pair() {
+        let json = r#""\uD83D\uDE00 \ud834\udd1e""#;
+        assert_eq!(parse(json).unwrap(), Value::String("😀 𝄞".to_string()));
+    }
+
+    #[test]
+    fn parse_string_with_lone_surrogates_rejected() {
+        let cases = [
+            (r#""\uD83D""#, 1),
+            (r#""\uDE00x""#, 1),
+            (r#""a\uD83Dx""#, 2),
+            (r#""\uD83D\n""#, 1),
+            (r#""\uD83D\u0041""#, 1),
+        ];
+        for (json, offset) in cases {
+            match parse(json) {
+                Err(Error::LoneSurrogate(p)) => assert_eq!(p.offset, offset, "{json}"),
+                other => panic!("{json}: expected LoneSurrogate, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_string_with_lone_surrogates_replaced() {
+        let options = ParseOptions::new().lone_surrogates(LoneSurrogates::Replace);
+        let json = r#""\uD83D\n|\uDE00|\uD83D\uD83D\uDE00|\uD800\u0041""#;
+        assert_eq!(
+            parse_with(json, &options).unwrap(),
+            Value::String("\u{FFFD}\n|\u{FFFD}|\u{FFFD}😀|\u{FFFD}A".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_string_with_truncated_low_surrogate() {
+        let json = r#""\uD83D\uDE""#;
+        assert!(matches!(parse(json), Err(Error::InvalidUnicodeEscape(ref p)) if p.offset == 7));
+    }
+
+    #[test]
+    fn parse_deeply_nested_input_fails_gracefully() {
+        let json = "[".repeat(500_000);
+        let err = parse(&json).unwrap_err();
+        assert!(matches!(err, Error::DepthLimitExceeded(ref p) if p.offset == 128));
+    }
+
+    #[test]
+    fn parse_default_depth_limit() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(128)).is_ok());
+        let err = parse(&nested(129)).unwrap_err();
+        assert!(matches!(err, Error::DepthLimitExceeded(ref p) if p.offset == 128));
+        assert!(nested(129).parse::<Value>().is_err());
+    }
+
+    #[test]
+    fn parse_with_raised_depth_limit() {
+        let json = format!("{}{}", "[".repeat(1000), "]".repeat(1000));
+        assert!(parse(&json).is_err());
+        let options = ParseOptions::new().max_depth(1000);
+        assert!(parse_with(&json, &options).is_ok());
+    }
+
+    #[test]
+    fn parse_with_max_input_size() {
+        let options = ParseOptions::new().max_input_size(8);
+        assert!(parse_with("[1, 2 ] ", &options).is_ok());
+        let err = parse_with("[1, 2, 3]", &options).unwrap_err();
+        assert!(matches!(err, Error::InputTooLarge(ref p) if p.offset == 8));
+        let err = parse_reader_with("[1, 2, 3]".as_bytes(), &options).unwrap_err();
+        assert!(matches!(err, Error::InputTooLarge(ref p) if p.offset == 8));
+    }
+
+    #[test]
+    fn parse_with_max_string_len() {
+        let options = ParseOptions::new().max_string_len(3);
+        assert!(parse_with(r#"{"abc": "\u3042"}"#, &options).is_ok());
+        let err = parse_with(r#"["ab", "abcd"]"#, &options).unwrap_err();
+        assert!(matches!(err, Error::StringTooLong(ref p) if p.offset == 7));
+        let err = parse_with(r#"{"abcd": 1}"#, &options).unwrap_err();
+        assert!(matches!(err, Error::StringTooLong(ref p) if p.offset == 1));
+        let err = parse_with(r#""\n\n\n\n""#, &options).unwrap_err();
+        assert!(matches!(err, Error::StringTooLong(_)));
+    }
+
+    #[test]
+    fn parse_with_max_members() {
+        let options = ParseOptions::new().max_members(2);
+        assert!(parse_with(r#"{"a": {"b": 1, "c": 2}, "d": [1, 2, 3]}"#, &options).is_ok());
+        let err = parse_with(r#"{"a": 1, "b": 2, "c": 3}"#, &options).unwrap_err();
+        assert!(matches!(err, Error::TooManyMembers(ref p) if p.offset == 17));
+    }
+
+    #[test]
+    fn parse_duplicate_keys_last_wins_by_default() {
+        let v = parse(r#"{"a": 1, "b": 2, "a": 3}"#).unwrap();
+        assert_eq!(v.to_string(), r#"{"a":3,"b":2}"#);
+    }
+
+    #[test]
+    fn parse_duplicate_keys_first_wins() {
+        let options = ParseOptions::new().duplicate_keys(DuplicateKeys::FirstWins);
+        let v = parse_with(r#"{"a": 1, "b": 2, "a": {"c": 3}}"#, &options).unwrap();
+        assert_eq!(v.to_string(), r#"{"a":1,"b":2}"#);
+    }
+
+    #[test]
+    fn parse_duplicate_keys_rejected() {
+        let options = ParseOptions::new().duplicate_keys(DuplicateKeys::Reject);
+        assert!(parse_with(r#"{"a": {"a": 1}, "b": [{"a": 2}]}"#, &options).is_ok());
+        let err = parse_with("{\"a\": 1,\n \"a\": 2}", &options).unwrap_err();
+        assert!(matches!(err, Error::DuplicateKey(_)));
+        assert_eq!(err.position().offset, 10);
+        assert_eq!(err.position().line, 2);
+        assert_eq!(err.position().excerpt, "\"a\": 2}");
+    }
+
+    #[test]
+    fn parse_duplicate_keys_collected() {
+        let options = ParseOptions::new().duplicate_keys(DuplicateKeys::Collect);
+        let json = r#"{"a": [0], "b": 1, "a": 2, "a": [3], "c": 4}"#;
+        let v = parse_with(json, &options).unwrap();
+        assert_eq!(v.to_string(), r#"{"a":[[0],2,[3]],"b":1,"c":4}"#);
+    }
+
+    #[test]
+    fn parse_borrowed_duplicate_keys_last_wins() {
+        let v = parse_borrowed(r#"{"k": 1, "k": 2}"#).unwrap();
+        assert_eq!(v["k"], BorrowedValue::Number(Number::PosInt(2)));
+    }
+
+    #[test]
+    fn parse_jsonc() {
+        let jsonc = r#"{
+    // Visit https://aka.ms/tsconfig to read more about this file
+    "compilerOptions": {
+        "target": "es2016", /* Set the JavaScript language version. */
+        "lib": ["dom", "es2016",],
+    },
+}"#;
+        let strict = r#"{"compilerOptions": {"target": "es2016", "lib": ["dom", "es2016"]}}"#;
+        assert_eq!(
+            parse_with(jsonc, &ParseOptions::jsonc()).unwrap(),
+            parse(strict).unwrap()
+        );
+        let file = std::io::Cursor::new(jsonc);
+        assert_eq!(
+            parse_reader_with(file, &ParseOptions::jsonc()).unwrap(),
+            parse(strict).unwrap()
+        );
+        assert!(parse(jsonc).is_err());
+    }
+
+    #[test]
+    fn parse_json5() {
+        let json5 = r#"// https://spec.json5.org/#short-example
+{
+  // comments
+  unquoted: 'and you can quote me on that',
+  singleQuotes: 'I can use "double quotes" here',
+  lineBreaks: "Look, Mom! \
+No \\n's!",
+  hexadecimal: 0xdecaf,
+  leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+  positiveSign: +1,
+  trailingComma: 'in objects', andIn: ['arrays',],
+  "backwardsCompatible": "with JSON",
+}"#;
+        let v = parse_with(json5, &ParseOptions::json5()).unwrap();
+        assert_eq!(
+            v["unquoted"],
+            Value::String("and you can quote me on that".into())
+        );
+        assert_eq!(
+            v["singleQuotes"],
+            Value::String(r#"I can use "double quotes" here"#.into())
+        );
+        assert_eq!(
+            v["lineBreaks"],
+            Value::String(r"Look, Mom! No \n's!".into())
+        );
+        assert_eq!(v["hexadecimal"], Value::Number(Number::PosInt(912559)));
+        assert_eq!(
+            v["leadingDecimalPoint"],
+            Value::Number(Number::Float(0.8675309))
+        );
+        assert_eq!(v["andTrailing"], Value::Number(Number::Float(8675309.0)));
+        assert_eq!(v["positiveSign"], Value::Number(Number::PosInt(1)));
+        assert_eq!(
+            v["andIn"],
+            Value::Array(vec![Value::String("arrays".into())])
+        );
+        assert_eq!(v["backwardsCompatible"], Value::String("with JSON".into()));
+
+        let file = std::io::Cursor::new(json5);
+        assert_eq!(parse_reader_with(file, &ParseOptions::json5()).unwrap(), v);
+    }
+
+    #[test]
+    fn parse_json5_identifier_names() {
+        let options = ParseOptions::json5();
+        let json5 = r"{π: 1, ünï: 2, \u0061b: 3, a\u0062c: 4, _\u00e9$: 5, x\u200Cy: 6}";
+        let v = parse_with(json5, &options).unwrap();
+        let Value::Object(ref obj) = v else {
+            panic!("Expected an object, got {:?}", v);
+        };
+        assert_eq!(
+            obj.keys().collect::<Vec<_>>(),
+            ["π", "ünï", "ab", "abc", "_é$", "x\u{200C}y"]
+        );
+        assert_eq!(parse_reader_with(json5.as_bytes(), &options).unwrap(), v);
+
+        for (json5, offset) in [
+            (r"{\u0031: 1}", 1),
+            ("{\u{663}: 1}", 1),
+            (r"{a\u002D: 1}", 2),
+            (r"{\uD800: 1}", 1),
+            ("{a\u{2013}: 1}", 2),
+        ] {
+            let err = parse_with(json5, &options).unwrap_err();
+            assert!(
+                matches!(err, Error::UnexpectedToken(ref p) if p.offset == offset),
+                "{json5}: {err:?}"
+            );
+        }
+        let err = parse_with(r"{\x61: 1}", &options).unwrap_err();
+        assert!(matches!(err, Error::InvalidEscapeSequence(ref s, _) if s == r"\x"));
+        assert!(parse("{π: 1}").is_err());
+    }
+
+    #[test]
+    fn parse_json5_matches_json() {
+        let json =
+            r#"{"a": [1, -0, 1.5e3, "\u00e9\n", true, null, {}], "b": -9223372036854775808}"#;
+        assert_eq!(
+            parse_with(json, &ParseOptions::json5()).unwrap(),
+            parse(json).unwrap()
+        );
+    }
+
+    fn recover(json: &str) -> (String, Vec<(usize, Error)>) {
+        let (v, errors) = parse_recovering(json, &ParseOptions::new());
+        let errors = errors
+            .into_iter()
+            .map(|e| (e.position().offset, e))
+            .collect();
+        (v.to_string(), errors)
+    }
+
+    #[test]
+    fn parse_recovering_valid() {
+        let json = r#"{"a": [1, 2, {"b": null}], "c": "d"}"#;
+        let (v, errors) = parse_recovering(json, &ParseOptions::new());
+        assert_eq!(v, parse(json).unwrap());
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn parse_recovering_at_commas() {
+        let (v, errors) = recover(r#"{"a": tru, "b": [1, 2 3, @, 4], "c" 5, "d": "\q", "e": 6}"#);
+        assert_eq!(v, r#"{"a":null,"b":[1,2,4],"d":null,"e":6}"#);
+        let offsets: Vec<_> = errors.iter().map(|(offset, _)| *offset).collect();
+        assert_eq!(offsets, [6, 22, 25, 36, 45]);
+        assert!(matches!(errors[0].1, Error::UnexpectedToken(_)));
+        assert!(matches!(errors[1].1, Error::MissingExpectedChar(']', _)));
+        assert!(matches!(errors[3].1, Error::MissingExpectedChar(':', _)));
+        assert!(matches!(errors[4].1, Error::InvalidEscapeSequence(..)));
+    }
+
+    #[test]
+    fn parse_recovering_at_brackets() {
+        let (v, errors) = recover(r#"{"a": [1, {"b": 2], "c": [3}"#);
+        assert_eq!(v, r#"{"a":[1,{"b":2}],"c":[3]}"#);
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0].1, Error::UnexpectedToken(_)));
+        assert!(matches!(errors[1].1, Error::MissingExpectedChar(']', _)));
+
+        let (v, errors) = recover("[1]]");
+        assert_eq!(v, "[1]");
+        assert!(matches!(errors[..], [(3, Error::TrailingCharacters(_))]));
+    }
+
+    #[test]
+    fn parse_recovering_unclosed() {
+        let (v, errors) = recover(r#"{"a": [1, {"b": "c"#);
+        assert_eq!(v, r#"{"a":[1,{"b":null}]}"#);
+        assert!(matches!(errors[..], [(16, Error::UnterminatedString(_))]));
+
+        let (v, errors) = recover(r#"["a", "b"#);
+        assert_eq!(v, r#"["a"]"#);
+        assert_eq!(errors.len(), 1);
+
+        let (v, errors) = recover("");
+        assert_eq!(v, "null");
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn parse_recovering_skips_nested_values() {
+        let options = ParseOptions::new().max_depth(2);
+        let json = r#"[[1], [[2, "]"]], [3], x]"#;
+        let (v, errors) = parse_recovering(json, &options);
+        assert_eq!(v.to_string(), "[[1],[],[3]]");
+        assert!(matches!(
+            errors[..],
+            [Error::DepthLimitExceeded(_), Error::UnexpectedToken(_)]
+        ));
+    }
+
+    #[test]
+    fn parse_recovering_duplicate_keys() {
+        let options = ParseOptions::new().duplicate_keys(DuplicateKeys::Reject);
+        let (v, errors) = parse_recovering(r#"{"a": 1, "a": 2, "b": 3, "b": 4}"#, &options);
+        assert_eq!(v.to_string(), r#"{"a":1,"b":3}"#);
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn parse_recovering_fatal_error() {
+        let options = ParseOptions::new().max_input_size(8);
+        let (v, errors) = parse_recovering("[1, 2, 3, 4, 5]", &options);
+        assert_eq!(v.to_string(), "[1,2]");
+        assert!(matches!(errors[..], [Error::InputTooLarge(_)]));
+
+        let jsonc = ParseOptions::jsonc();
+        let (v, errors) = parse_recovering("[1, x /* ], */, 2 /]", &jsonc);
+        assert_eq!(v.to_string(), "[1,2]");
+        assert_eq!(errors.len(), 2);
+
+        let file = std::io::Cursor::new(r#"{"a": 1, "b": }"#);
+        let (v, errors) = parse_reader_recovering(file, &ParseOptions::new());
+        assert_eq!(v.to_string(), r#"{"a":1,"b":null}"#);
+        assert_eq!(errors.len(), 1);
+    }
+
+    fn partial(json: &str) -> (String, Option<Vec<PathSegment>>) {
+        let Partial { value, incomplete } = parse_partial(json, &ParseOptions::new()).unwrap();
+        (value.to_string(), incomplete)
+    }
+
+    fn key(k: &str) -> PathSegment {
+        PathSegment::Key(k.to_string())
+    }
+
+    #[test]
+    fn parse_partial_complete() {
+        assert_eq!(
+            partial(r#"{"a": [1, "b"]} "#),
+            (r#"{"a":[1,"b"]}"#.into(), None)
+        );
+    }
+
+    #[test]
+    fn parse_partial_strings() {
+        let json = r#"{"choices": [{"text": "Hello, wor"#;
+        assert_eq!(
+            partial(json),
+            (
+                r#"{"choices":[{"text":"Hello, wor"}]}"#.into(),
+                Some(vec![key("choices"), PathSegment::Index(0), key("text")])
+            )
+        );
+        assert_eq!(
+            partial(r#"["a", "b\"#),
+            (r#"["a","b"]"#.into(), Some(vec![PathSegment::Index(1)]))
+        );
+        assert_eq!(partial(r#"["\u00e9\u00"#).0, "[\"\u{e9}\"]");
+        assert_eq!(partial(r#""abc"#), (r#""abc""#.into(), Some(vec![])));
+    }
+
+    #[test]
+    fn parse_partial_drops_cut_tokens() {
+        assert_eq!(
+            partial(r#"{"a": 1, "b": tr"#),
+            (r#"{"a":1}"#.into(), Some(vec![key("b")]))
+        );
+        assert_eq!(
+            partial(r#"{"a": 1, "b": "#),
+            (r#"{"a":1}"#.into(), Some(vec![key("b")]))
+        );
+        assert_eq!(
+            partial(r#"{"a": 1, "b"#),
+            (r#"{"a":1}"#.into(), Some(vec![]))
+        );
+        assert_eq!(
+            partial("[[1, -"),
+            (
+                "[[1]]".into(),
+                Some(vec![PathSegment::Index(0), PathSegment::Index(1)])
+            )
+        );
+        assert_eq!(partial("[[1], "), ("[[1]]".into(), Some(vec![])));
+        assert_eq!(
+            partial("[1.5e"),
+            ("[]".into(), Some(vec![PathSegment::Index(0)]))
+        );
+        assert_eq!(partial(""), ("null".into(), Some(vec![])));
+        assert_eq!(partial("nu"), ("null".into(), Some(vec![])));
+    }
+
+    #[test]
+    fn parse_partial_errors() {
+        let options = ParseOptions::new();
+        assert!(matches!(
+            parse_partial("[1 2", &options),
+            Err(Error::MissingExpectedChar(']', _))
+        ));
+        assert!(matches!(
+            parse_partial(r#"{"a": x"#, &options),
+            Err(Error::UnexpectedToken(_))
+        ));
+        assert!(matches!(
+            parse_partial("[1]]", &options),
+            Err(Error::TrailingCharacters(_))
+        ));
+        let jsonc = ParseOptions::jsonc();
+        let p = parse_partial("[1, /* two", &jsonc).unwrap();
+        assert_eq!(p.value.to_string(), "[1]");
+    }
+
+    fn documents(json: &str) -> Vec<(usize, String)> {
+        let mut docs = Documents::new(Reader::new(json));
+        let mut out = Vec::new();
+        while let Some(doc) = docs.next() {
+            let doc = match doc {
+                Ok(v) => v.to_string(),
+                Err(e) => format!("error at {}", e.position().offset),
+            };
+            out.push((docs.offset(), doc));
+        }
+        out
+    }
+
+    #[test]
+    fn parse_concatenated_documents() {
+        assert_eq!(
+            documents(r#"{"a":1}{"b":2}[3]"x"4 5 "#),
+            [
+                (0, r#"{"a":1}"#.into()),
+                (7, r#"{"b":2}"#.into()),
+                (14, "[3]".into()),
+                (17, r#""x""#.into()),
+                (20, "4".into()),
+                (22, "5".into()),
+            ]
+        );
+        assert_eq!(
+            documents("\n{\"status\": \"start\"}\n{\"status\": \"die\"}\n"),
+            [
+                (1, r#"{"status":"start"}"#.into()),
+                (21, r#"{"status":"die"}"#.into()),
+            ]
+        );
+        assert!(documents("  \n").is_empty());
+    }
+
+    #[test]
+    fn parse_json_text_sequence() {
+        let json = "\x1E{\"a\": 1}\n\x1E[2]\n\x1E\"three\"\n";
+        assert_eq!(
+            documents(json),
+            [
+                (1, r#"{"a":1}"#.into()),
+                (11, "[2]".into()),
+                (16, r#""three""#.into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_documents_skip_to_record_after_error() {
+        let json = "\x1E{\"a\": \x1E{\"b\": [}\n\x1E[tr\x1E4\n";
+        assert_eq!(
+            documents(json),
+            [
+                (1, "error at 7".into()),
+                (8, "error at 15".into()),
+                (18, "error at 19".into()),
+                (22, "4".into()),
+            ]
+        );
+
+        // Without separators, an error ends the sequence.
+        assert_eq!(
+            documents("[1] [2 3] [4]"),
+            [(0, "[1]".into()), (4, "error at 7".into())]
+        );
+    }
+
+    #[test]
+    fn parse_documents_from_reader() {
+        let file = std::io::Cursor::new("1 {}\x1E[]");
+        let docs = Documents::new(Reader::from_reader(file))
+            .with_options(ParseOptions::new().max_depth(0));
+        let results: Vec<_> = docs.map(|doc| doc.map(|v| v.to_string())).collect();
+        assert_eq!(results[0], Ok("1".to_string()));
+        assert!(matches!(results[1], Err(Error::DepthLimitExceeded(_))));
+        assert!(matches!(results[2], Err(Error::DepthLimitExceeded(_))));
+        assert_eq!(results.len(), 3);
+    }
 }
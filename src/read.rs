@@ -0,0 +1,225 @@
+//! Byte sources a [`Reader`](crate::reader::Reader) can pull input from.
+
+use std::io;
+
+use crate::parse::{EXCERPT_LEN, Error, Position, Result};
+
+/// Size of the buffer [`IoRead`] refills from the underlying reader.
+const BUF_SIZE: usize = 8 * 1024;
+
+/// Location of a byte in the input.
+///
+/// Line and columns are tracked while reading, so sources that forget
+/// consumed input can still report where an error happened.
+#[derive(Debug, Clone, Copy)]
+pub struct Mark {
+    offset: usize,
+    line: usize,
+    column: usize,
+    column_utf16: usize,
+}
+
+impl Mark {
+    /// Byte offset from the start of the input.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn start() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+            column_utf16: 1,
+        }
+    }
+
+    fn advance(&mut self, b: u8) {
+        self.offset += 1;
+        if b == b'\n' {
+            self.line += 1;
+            self.column = 1;
+            self.column_utf16 = 1;
+            return;
+        }
+        self.column += 1;
+        match b {
+            0x80..=0xBF => {}                 // continuation byte
+            0xF0.. => self.column_utf16 += 2, // encoded as a surrogate pair
+            _ => self.column_utf16 += 1,
+        }
+    }
+
+    fn position(self, excerpt: &[u8]) -> Position {
+        // Every char is at most 4 bytes, so this never cuts into the excerpt.
+        let excerpt = &excerpt[..excerpt.len().min(EXCERPT_LEN * 4)];
+        Position {
+            offset: self.offset,
+            line: self.line,
+            column: self.column,
+            column_utf16: self.column_utf16,
+            excerpt: String::from_utf8_lossy(excerpt)
+                .chars()
+                .take(EXCERPT_LEN)
+                .collect(),
+        }
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// A source of bytes for the parser.
+///
+/// `'de` is the lifetime of the input when the source can hand out strings
+/// borrowed from it.
+pub trait Read<'de>: private::Sealed {
+    /// Returns the next byte without consuming it.
+    fn peek(&mut self) -> Result<Option<u8>>;
+
+    /// Consumes the byte returned by the last call to `peek`.
+    fn discard(&mut self);
+
+    /// Location of the next byte.
+    fn mark(&self) -> Mark;
+
+    /// Error position for a location previously returned by `mark`.
+    fn position(&self, mark: Mark) -> Position;
+
+    /// Makes `peek` fail once more than `max_len` bytes have been read.
+    fn set_max_len(&mut self, max_len: usize);
+
+    /// The input between two byte offsets, if the source still has it.
+    fn slice(&self, start: usize, end: usize) -> Option<&'de str>;
+
+    fn next(&mut self) -> Result<Option<u8>> {
+        let b = self.peek()?;
+        if b.is_some() {
+            self.discard();
+        }
+        Ok(b)
+    }
+}
+
+/// Reads from an input that is already in memory.
+pub struct StrRead<'a> {
+    input: &'a str,
+    mark: Mark,
+    max_len: usize,
+}
+
+impl<'a> StrRead<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            mark: Mark::start(),
+            max_len: usize::MAX,
+        }
+    }
+}
+
+impl private::Sealed for StrRead<'_> {}
+
+impl<'a> Read<'a> for StrRead<'a> {
+    fn peek(&mut self) -> Result<Option<u8>> {
+        let b = self.input.as_bytes().get(self.mark.offset).copied();
+        if b.is_some() && self.mark.offset >= self.max_len {
+            return Err(Error::InputTooLarge(self.position(self.mark)));
+        }
+        Ok(b)
+    }
+
+    fn discard(&mut self) {
+        self.mark.advance(self.input.as_bytes()[self.mark.offset]);
+    }
+
+    fn mark(&self) -> Mark {
+        self.mark
+    }
+
+    fn position(&self, mark: Mark) -> Position {
+        mark.position(&self.input.as_bytes()[mark.offset..])
+    }
+
+    fn set_max_len(&mut self, max_len: usize) {
+        self.max_len = max_len;
+    }
+
+    fn slice(&self, start: usize, end: usize) -> Option<&'a str> {
+        self.input.get(start..end)
+    }
+}
+
+/// Reads from an [`io::Read`] through a fixed-size buffer, so memory use does
+/// not grow with the size of the input.
+pub struct IoRead<R> {
+    reader: R,
+    buf: Box<[u8]>,
+    pos: usize,
+    len: usize,
+    mark: Mark,
+    max_len: usize,
+}
+
+impl<R: io::Read> IoRead<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: vec![0; BUF_SIZE].into_boxed_slice(),
+            pos: 0,
+            len: 0,
+            mark: Mark::start(),
+            max_len: usize::MAX,
+        }
+    }
+}
+
+impl<R> private::Sealed for IoRead<R> {}
+
+impl<'de, R: io::Read> Read<'de> for IoRead<R> {
+    fn peek(&mut self) -> Result<Option<u8>> {
+        while self.pos == self.len {
+            match self.reader.read(&mut self.buf) {
+                Ok(0) => return Ok(None),
+                Ok(n) => {
+                    self.pos = 0;
+                    self.len = n;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(Error::Io(e.to_string(), self.position(self.mark))),
+            }
+        }
+        if self.mark.offset >= self.max_len {
+            return Err(Error::InputTooLarge(self.position(self.mark)));
+        }
+        Ok(Some(self.buf[self.pos]))
+    }
+
+    fn discard(&mut self) {
+        self.mark.advance(self.buf[self.pos]);
+        self.pos += 1;
+    }
+
+    fn mark(&self) -> Mark {
+        self.mark
+    }
+
+    fn position(&self, mark: Mark) -> Position {
+        // Only the current buffer is still around to take the excerpt from.
+        let buf_start = self.mark.offset - self.pos;
+        let excerpt = match mark.offset.checked_sub(buf_start) {
+            Some(i) if i <= self.len => &self.buf[i..self.len],
+            _ => &[],
+        };
+        mark.position(excerpt)
+    }
+
+    fn set_max_len(&mut self, max_len: usize) {
+        self.max_len = max_len;
+    }
+
+    fn slice(&self, _start: usize, _end: usize) -> Option<&'de str> {
+        None
+    }
+}
@@ -0,0 +1,1399 @@
+//! Pull-based access to the tokens of a JSON document.
+//!
+//! ```
+//! use rj::reader::{Event, Reader};
+//!
+//! let mut reader = Reader::new(r#"{"ids": [1, 2]}"#);
+//! assert_eq!(reader.next().unwrap().unwrap(), Event::StartObject);
+//! assert_eq!(reader.next().unwrap().unwrap(), Event::Key("ids".into()));
+//! assert_eq!(reader.next().unwrap().unwrap(), Event::StartArray);
+//! ```
+
+use std::{borrow::Cow, io, marker::PhantomData};
+
+use crate::Number;
+use crate::parse::{Error, LoneSurrogates, ParseOptions, Position, Result};
+use crate::read::{IoRead, Mark, Read, StrRead};
+
+/// A token of a JSON document.
+///
+/// Strings are borrowed from the input when it is in memory and contains no
+/// escape sequences.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    /// An object member name. The member value follows as the next event.
+    Key(Cow<'a, str>),
+    String(Cow<'a, str>),
+    Number(Number),
+    Bool(bool),
+    Null,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Object,
+    Array,
+}
+
+/// What the reader is looking for next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Value,
+    /// A value or `]` right after `[`.
+    FirstElement,
+    /// A key or `}` right after `{`.
+    FirstMember,
+    /// `,` or the end of the innermost container.
+    CommaOrEnd,
+    /// The document is complete; only whitespace may follow.
+    Done,
+    /// An error was reported and no more events will follow.
+    Failed,
+}
+
+/// Reads a single JSON document as a sequence of [`Event`]s.
+///
+/// Nesting is tracked on the heap, so deeply nested input does not grow the
+/// call stack. It is still limited by [`ParseOptions::max_depth`].
+pub struct Reader<'de, R> {
+    read: R,
+    /// Open containers, innermost last, with the number of members read.
+    stack: Vec<(Container, usize)>,
+    state: State,
+    options: ParseOptions,
+    /// Where the token of the most recent event starts.
+    event_start: Mark,
+    /// The quote of a string that was left unfinished by an error.
+    open_quote: Option<u8>,
+    /// Containers to close with end events after recovering from an error.
+    unclosed: usize,
+    /// Whether the end of the input may cut the document short.
+    partial: bool,
+    /// Where the token the input ended in starts, once it has.
+    truncated_at: Option<Mark>,
+    /// Whether more documents may follow the first.
+    multiple: bool,
+    _input: PhantomData<&'de ()>,
+}
+
+impl<'a> Reader<'a, StrRead<'a>> {
+    pub fn new(input: &'a str) -> Self {
+        Self::with_read(StrRead::new(input))
+    }
+}
+
+impl<R: io::Read> Reader<'static, IoRead<R>> {
+    /// Reads from `reader` through a fixed-size buffer.
+    pub fn from_reader(reader: R) -> Self {
+        Self::with_read(IoRead::new(reader))
+    }
+}
+
+/// The record separator that starts each text of an RFC 7464 JSON text
+/// sequence.
+const RS: u8 = 0x1E;
+
+/// whitespace = \x20 \x09 \x0a \x0d
+/// - \x20 space
+/// - \x09 horizontal tab
+/// - \x0a line feed or new line
+/// - \x0d carriage return
+fn is_whitespace(b: u8) -> bool {
+    b == b'\x20' || b == b'\x09' || b == b'\x0a' || b == b'\x0d'
+}
+
+/// Whitespace JSON5 allows beyond JSON's: the other ECMAScript white space and
+/// line terminator characters.
+fn is_json5_whitespace(c: char) -> bool {
+    matches!(
+        c,
+        '\x0B' | '\x0C' | '\u{A0}' | '\u{1680}' | '\u{2000}'
+            ..='\u{200A}'
+                | '\u{2028}'
+                | '\u{2029}'
+                | '\u{202F}'
+                | '\u{205F}'
+                | '\u{3000}'
+                | '\u{FEFF}'
+    )
+}
+
+/// Whether `b` may start a JSON5 member name, if it is ASCII. Escapes are
+/// checked once decoded.
+fn is_identifier_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || matches!(b, b'_' | b'$' | b'\\')
+}
+
+fn is_identifier_part(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '$' | '\u{200C}' | '\u{200D}')
+}
+
+/// Number of bytes in the UTF-8 sequence starting with `b`, or 0 if `b`
+/// cannot start one.
+fn utf8_width(b: u8) -> usize {
+    match b {
+        0x00..=0x7F => 1,
+        0xC2..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF4 => 4,
+        _ => 0,
+    }
+}
+
+impl<'de, R: Read<'de>> Reader<'de, R> {
+    fn with_read(read: R) -> Self {
+        Self {
+            event_start: read.mark(),
+            read,
+            open_quote: None,
+            unclosed: 0,
+            partial: false,
+            truncated_at: None,
+            multiple: false,
+            stack: Vec::new(),
+            state: State::Value,
+            options: ParseOptions::default(),
+            _input: PhantomData,
+        }
+    }
+
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.read.set_max_len(options.max_input_size);
+        self.options = options;
+        self
+    }
+
+    /// Number of containers the next event is nested in.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Location of the next unread byte.
+    pub fn mark(&self) -> Mark {
+        self.read.mark()
+    }
+
+    /// Where the token of the event last returned by `next_event` starts.
+    pub fn event_position(&self) -> Position {
+        self.read.position(self.event_start)
+    }
+
+    /// Where the token of the event last returned by `next_event` starts.
+    pub(crate) fn event_start(&self) -> Mark {
+        self.event_start
+    }
+
+    pub(crate) fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    /// Reads a prefix of a document: if the input ends in the middle, the
+    /// string being read is cut off there, a token other than a string is
+    /// dropped, and all open containers are closed.
+    pub(crate) fn partial(mut self) -> Self {
+        self.partial = true;
+        self
+    }
+
+    /// Where the token that was cut off by the end of the input starts, or
+    /// the end of the input if it ended between tokens.
+    pub(crate) fn truncated_at(&self) -> Option<Mark> {
+        self.truncated_at
+    }
+
+    /// Reads a sequence of documents. After each document `next_event`
+    /// returns `None` once, and then goes on with the next.
+    ///
+    /// Documents may follow each other directly or be separated by
+    /// whitespace or record separators (U+001E).
+    pub(crate) fn multiple(mut self) -> Self {
+        self.multiple = true;
+        self
+    }
+
+    /// Skips whitespace and returns whether the input ends there.
+    pub(crate) fn at_end(&mut self) -> Result<bool> {
+        Ok(self.peek_token()?.is_none())
+    }
+
+    /// Skips to the next record separator after an error in a sequence of
+    /// documents, and returns whether one was found to go on from.
+    pub(crate) fn skip_to_record(&mut self) -> Result<bool> {
+        while let Some(b) = self.read.peek()? {
+            if b == RS {
+                self.stack.clear();
+                self.state = State::Value;
+                self.open_quote = None;
+                return Ok(true);
+            }
+            self.read.discard();
+        }
+        Ok(false)
+    }
+
+    /// Returns the next event, or `None` once the document and any trailing
+    /// whitespace have been read.
+    pub fn next_event(&mut self) -> Result<Option<Event<'de>>> {
+        let mut res = self.step();
+        if res.is_err() && self.partial && matches!(self.read.peek(), Ok(None)) {
+            self.truncated_at.get_or_insert(self.event_start);
+            self.unclosed = self.stack.len();
+            self.state = State::Done;
+            res = self.step();
+        }
+        if res.is_err() {
+            self.state = State::Failed;
+        }
+        res
+    }
+
+    /// Skips ahead after `next_event` failed, so that reading can go on and
+    /// report further errors.
+    ///
+    /// Input is skipped up to the next `,` or closing bracket of an open
+    /// container. Containers still open at the end of the input are closed.
+    /// Returns an error that makes reading on impossible, such as a failure
+    /// of the underlying reader.
+    pub(crate) fn recover(&mut self) -> Option<Error> {
+        let res = self.skip_to_resume();
+        if res.is_err() || self.state != State::CommaOrEnd {
+            self.unclosed = self.stack.len();
+        }
+        res.err()
+    }
+
+    fn skip_to_resume(&mut self) -> Result<()> {
+        self.state = State::Failed;
+        if let Some(quote) = self.open_quote.take() {
+            self.skip_string(quote)?;
+        }
+        // Containers opened in the skipped input.
+        let mut depth = 0;
+        while let Some(b) = self.read.peek()? {
+            match b {
+                b'"' => {
+                    self.read.discard();
+                    self.skip_string(b)?;
+                    continue;
+                }
+                b'\'' if self.options.json5_syntax => {
+                    self.read.discard();
+                    self.skip_string(b)?;
+                    continue;
+                }
+                b'/' if self.options.allow_comments => {
+                    // A lone `/` is skipped along with the byte after it.
+                    if let Err(e @ (Error::Io(..) | Error::InputTooLarge(_))) = self.comment() {
+                        return Err(e);
+                    }
+                    continue;
+                }
+                b'[' | b'{' => depth += 1,
+                b']' | b'}' if depth > 0 => depth -= 1,
+                b',' if depth == 0 && !self.stack.is_empty() => {
+                    self.state = State::CommaOrEnd;
+                    return Ok(());
+                }
+                b']' | b'}' => {
+                    let container = match b {
+                        b']' => Container::Array,
+                        _ => Container::Object,
+                    };
+                    // Close inner containers that are missing their end.
+                    if let Some(i) = self.stack.iter().rposition(|&(c, _)| c == container) {
+                        self.unclosed = self.stack.len() - 1 - i;
+                        self.state = State::CommaOrEnd;
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+            self.read.discard();
+        }
+        self.state = State::Done;
+        Ok(())
+    }
+
+    /// Skips the rest of a string whose opening `quote` has been read, up to
+    /// the closing quote or the end of the line.
+    fn skip_string(&mut self, quote: u8) -> Result<()> {
+        while let Some(b) = self.read.peek()? {
+            if b == b'\n' {
+                break;
+            }
+            self.read.discard();
+            if b == quote {
+                break;
+            }
+            if b == b'\\' && self.read.peek()?.is_some_and(|b| b != b'\n') {
+                self.read.discard();
+            }
+        }
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<Option<Event<'de>>> {
+        if self.unclosed > 0 {
+            self.unclosed -= 1;
+            return Ok(match self.stack.pop() {
+                Some((Container::Object, _)) => Some(Event::EndObject),
+                Some((Container::Array, _)) => Some(Event::EndArray),
+                None => unreachable!("only open containers are closed"),
+            });
+        }
+        let mut lead = None;
+        if !matches!(self.state, State::Done | State::Failed) {
+            lead = self.skip_whitespace()?;
+            self.event_start = lead.map_or(self.read.mark(), |(_, mark)| mark);
+        }
+        if let Some((_, mark)) = lead {
+            // Only a member name may start with a character read as a lead.
+            if !matches!(self.state, State::FirstMember) {
+                return Err(Error::UnexpectedToken(self.read.position(mark)));
+            }
+        }
+        match self.state {
+            State::Value => self.value().map(Some),
+            State::FirstElement => {
+                if self.eat(b']')? {
+                    self.end_container();
+                    Ok(Some(Event::EndArray))
+                } else {
+                    self.value().map(Some)
+                }
+            }
+            State::FirstMember => {
+                if lead.is_none() && self.eat(b'}')? {
+                    self.end_container();
+                    Ok(Some(Event::EndObject))
+                } else {
+                    self.key(lead).map(Some)
+                }
+            }
+            State::CommaOrEnd => match self.stack.last() {
+                Some((Container::Array, _)) => {
+                    if self.eat(b',')? {
+                        if self.options.allow_trailing_commas && self.eat(b']')? {
+                            self.end_container();
+                            return Ok(Some(Event::EndArray));
+                        }
+                        self.value().map(Some)
+                    } else if self.eat(b']')? {
+                        self.end_container();
+                        Ok(Some(Event::EndArray))
+                    } else {
+                        Err(Error::MissingExpectedChar(']', self.current_position()))
+                    }
+                }
+                Some((Container::Object, _)) => {
+                    if self.eat(b',')? {
+                        let lead = self.skip_whitespace()?;
+                        if lead.is_none() && self.options.allow_trailing_commas && self.eat(b'}')? {
+                            self.end_container();
+                            return Ok(Some(Event::EndObject));
+                        }
+                        self.key(lead).map(Some)
+                    } else if self.eat(b'}')? {
+                        self.end_container();
+                        Ok(Some(Event::EndObject))
+                    } else {
+                        Err(Error::UnexpectedToken(self.current_position()))
+                    }
+                }
+                None => unreachable!("CommaOrEnd is only set inside a container"),
+            },
+            State::Done => {
+                if self.peek_token()?.is_some() {
+                    if self.multiple {
+                        self.state = State::Value;
+                        return Ok(None);
+                    }
+                    return Err(Error::TrailingCharacters(self.current_position()));
+                }
+                Ok(None)
+            }
+            State::Failed => Ok(None),
+        }
+    }
+
+    fn current_position(&self) -> Position {
+        self.read.position(self.read.mark())
+    }
+
+    /// Skips whitespace (and comments, if allowed) and returns the byte that
+    /// follows, without consuming it.
+    fn peek_token(&mut self) -> Result<Option<u8>> {
+        if let Some((_, mark)) = self.skip_whitespace()? {
+            return Err(Error::UnexpectedToken(self.read.position(mark)));
+        }
+        self.read.peek()
+    }
+
+    /// Skips whitespace and comments. In JSON5, telling other non-ASCII
+    /// characters from whitespace means reading them, so the first one is
+    /// returned with where it started.
+    fn skip_whitespace(&mut self) -> Result<Option<(char, Mark)>> {
+        while let Some(b) = self.read.peek()? {
+            if b == b'/' && self.options.allow_comments {
+                self.comment()?;
+                continue;
+            }
+            if self.options.json5_syntax && (b == b'\x0B' || b == b'\x0C' || b >= 0x80) {
+                let mark = self.read.mark();
+                self.read.discard();
+                let c = utf8_char(&mut self.read, b, mark)?;
+                if !is_json5_whitespace(c) {
+                    return Ok(Some((c, mark)));
+                }
+                continue;
+            }
+            // A record separator inside a document means it was cut off.
+            let separator = b == RS && self.multiple && self.stack.is_empty();
+            if !is_whitespace(b) && !separator {
+                break;
+            }
+            self.read.discard();
+        }
+        Ok(None)
+    }
+
+    /// Skips a `// line` or `/* block */` comment.
+    fn comment(&mut self) -> Result<()> {
+        let start = self.read.mark();
+        self.read.discard();
+        match self.read.next()? {
+            Some(b'/') => {
+                while let Some(b) = self.read.next()? {
+                    if b == b'\n' {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+            Some(b'*') => {
+                let mut star = false;
+                while let Some(b) = self.read.next()? {
+                    if star && b == b'/' {
+                        return Ok(());
+                    }
+                    star = b == b'*';
+                }
+                Err(Error::UnterminatedComment(self.read.position(start)))
+            }
+            _ => Err(Error::UnexpectedToken(self.read.position(start))),
+        }
+    }
+
+    /// Skips whitespace and consumes `b` if the input continues with it.
+    fn eat(&mut self, b: u8) -> Result<bool> {
+        if self.peek_token()? == Some(b) {
+            self.read.discard();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn expect(&mut self, expected_char: u8) -> Result<()> {
+        if self.eat(expected_char)? {
+            Ok(())
+        } else {
+            Err(Error::MissingExpectedChar(
+                expected_char as char,
+                self.current_position(),
+            ))
+        }
+    }
+
+    /// Consumes the opening bracket of `container`.
+    fn start_container(&mut self, container: Container) -> Result<()> {
+        if self.stack.len() >= self.options.max_depth {
+            return Err(Error::DepthLimitExceeded(self.current_position()));
+        }
+        self.read.discard();
+        self.stack.push((container, 0));
+        self.state = match container {
+            Container::Object => State::FirstMember,
+            Container::Array => State::FirstElement,
+        };
+        Ok(())
+    }
+
+    fn end_container(&mut self) {
+        self.stack.pop();
+        self.end_value();
+    }
+
+    fn end_value(&mut self) {
+        self.state = if self.stack.is_empty() {
+            State::Done
+        } else {
+            State::CommaOrEnd
+        };
+    }
+
+    fn value(&mut self) -> Result<Event<'de>> {
+        let next = self.peek_token()?;
+        self.event_start = self.read.mark();
+        let json5 = self.options.json5_syntax;
+        let event = match next {
+            Some(b'f') => self.keyword("false", Event::Bool(false))?,
+            Some(b'n') => self.keyword("null", Event::Null)?,
+            Some(b't') => self.keyword("true", Event::Bool(true))?,
+            Some(b'{') => {
+                self.start_container(Container::Object)?;
+                return Ok(Event::StartObject);
+            }
+            Some(b'[') => {
+                self.start_container(Container::Array)?;
+                return Ok(Event::StartArray);
+            }
+            Some(b'"') => Event::String(self.string()?),
+            Some(b'-' | b'0'..=b'9') => self.number()?,
+            Some(b'\'') if json5 => Event::String(self.string()?),
+            Some(b'+' | b'.' | b'I' | b'N') if json5 => self.number()?,
+            _ => return Err(Error::UnexpectedToken(self.current_position())),
+        };
+        self.end_value();
+        Ok(event)
+    }
+
+    /// Reads a member name and its colon. `lead` is a non-ASCII character
+    /// that [`skip_whitespace`](Self::skip_whitespace) read before it.
+    fn key(&mut self, lead: Option<(char, Mark)>) -> Result<Event<'de>> {
+        self.event_start = lead.map_or(self.read.mark(), |(_, mark)| mark);
+        if let Some((_, members)) = self.stack.last_mut() {
+            *members += 1;
+            if *members > self.options.max_members {
+                return Err(Error::TooManyMembers(self.current_position()));
+            }
+        }
+        let key = match (lead, self.read.peek()?) {
+            (Some(_), _) => self.identifier(lead)?,
+            (None, Some(b)) if self.options.json5_syntax && is_identifier_start(b) => {
+                self.identifier(None)?
+            }
+            _ => self.string()?,
+        };
+        self.expect(b':')?;
+        self.state = State::Value;
+        Ok(Event::Key(key))
+    }
+
+    fn keyword(&mut self, word: &str, event: Event<'de>) -> Result<Event<'de>> {
+        let start = self.read.mark();
+        for expected in word.bytes() {
+            if self.read.peek()? != Some(expected) {
+                return Err(Error::UnexpectedToken(self.read.position(start)));
+            }
+            self.read.discard();
+        }
+        Ok(event)
+    }
+
+    /// An unquoted JSON5 member name.
+    /// A JSON5 member name: letters, digits, `_`, `$` and `\uXXXX` escapes
+    /// of them, not starting with a digit. `lead` is a first character that
+    /// has already been read.
+    fn identifier(&mut self, mut lead: Option<(char, Mark)>) -> Result<Cow<'de, str>> {
+        let start = lead.map_or(self.read.mark(), |(_, mark)| mark);
+        let mut name = String::new();
+        let mut escaped = false;
+        let end = loop {
+            if name.len() > self.options.max_string_len {
+                return Err(Error::StringTooLong(self.read.position(start)));
+            }
+            let (c, mark) = match lead.take() {
+                Some(lead) => lead,
+                None => {
+                    let mark = self.read.mark();
+                    let c = match self.read.peek()? {
+                        Some(b) if b.is_ascii_alphanumeric() || b == b'_' || b == b'$' => {
+                            self.read.discard();
+                            b as char
+                        }
+                        Some(b'\\') => {
+                            escaped = true;
+                            self.identifier_escape(mark)?
+                        }
+                        Some(b) if b >= 0x80 => {
+                            self.read.discard();
+                            let c = utf8_char(&mut self.read, b, mark)?;
+                            if is_json5_whitespace(c) {
+                                break mark;
+                            }
+                            c
+                        }
+                        _ => break mark,
+                    };
+                    (c, mark)
+                }
+            };
+            let allowed = match name.is_empty() {
+                true => c.is_alphabetic() || c == '_' || c == '$',
+                false => is_identifier_part(c),
+            };
+            if !allowed {
+                return Err(Error::UnexpectedToken(self.read.position(mark)));
+            }
+            name.push(c);
+        };
+        Ok(match self.read.slice(start.offset(), end.offset()) {
+            Some(s) if !escaped => Cow::Borrowed(s),
+            _ => Cow::Owned(name),
+        })
+    }
+
+    /// Decodes the `\uXXXX` escape at `mark` in a member name.
+    fn identifier_escape(&mut self, mark: Mark) -> Result<char> {
+        self.read.discard();
+        match self.read.next()? {
+            Some(b'u') => {}
+            other => {
+                let escaped = other.map_or(String::new(), |b| (b as char).to_string());
+                return Err(Error::InvalidEscapeSequence(
+                    format!("\\{escaped}"),
+                    self.read.position(mark),
+                ));
+            }
+        }
+        char::from_u32(self.hex(4, mark)?)
+            .ok_or_else(|| Error::UnexpectedToken(self.read.position(mark)))
+    }
+
+    fn string(&mut self) -> Result<Cow<'de, str>> {
+        let start = self.read.mark();
+        let quote = match self.read.peek()? {
+            Some(b'"') => b'"',
+            Some(b'\'') if self.options.json5_syntax => b'\'',
+            _ => return Err(Error::MissingExpectedChar('"', self.current_position())),
+        };
+        self.read.discard();
+        self.open_quote = Some(quote);
+
+        // Stays `None` while the string can still be borrowed from the input.
+        let content = self.read.mark().offset();
+        let mut parsed_string = match self.read.slice(content, content) {
+            Some(_) => None,
+            None => Some(String::new()),
+        };
+
+        loop {
+            let mark = self.read.mark();
+            let len = match &parsed_string {
+                Some(s) => s.len(),
+                None => mark.offset() - content,
+            };
+            if len > self.options.max_string_len {
+                return Err(Error::StringTooLong(self.read.position(start)));
+            }
+            let Some(b) = self.read.next()? else {
+                if self.partial {
+                    return Ok(self.cut_string(content, mark, len, parsed_string));
+                }
+                return Err(Error::UnterminatedString(self.read.position(start)));
+            };
+
+            let res = match b {
+                b'"' | b'\'' if b == quote => {
+                    self.open_quote = None;
+                    return Ok(match parsed_string {
+                        Some(s) => Cow::Owned(s),
+                        None => Cow::Borrowed(self.read.slice(content, mark.offset()).unwrap()),
+                    });
+                }
+                b'\\' => {
+                    let parsed_string = parsed_string.get_or_insert_with(|| {
+                        self.read.slice(content, mark.offset()).unwrap().to_string()
+                    });
+                    match self.read.next()? {
+                        Some(escaped_char) => self.escape(escaped_char, mark, parsed_string),
+                        None => Err(Error::InvalidEscapeSequence(
+                            "\\".to_string(),
+                            self.read.position(mark),
+                        )),
+                    }
+                }
+                b'\n' | b'\r' => {
+                    return Err(Error::UnexpectedToken(self.read.position(mark)));
+                }
+                // JSON5 allows the other control characters unescaped.
+                b if b < 0x20 && !self.options.json5_syntax => {
+                    return Err(Error::UnexpectedToken(self.read.position(mark)));
+                }
+                _ => utf8_char(&mut self.read, b, mark).map(|c| {
+                    if let Some(s) = &mut parsed_string {
+                        s.push(c);
+                    }
+                }),
+            };
+            if let Err(e) = res {
+                if self.partial && self.read.peek()?.is_none() {
+                    return Ok(self.cut_string(content, mark, len, parsed_string));
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    /// The part of a string before `mark`, where the input ended. `len` is
+    /// its length in bytes.
+    fn cut_string(
+        &mut self,
+        content: usize,
+        mark: Mark,
+        len: usize,
+        parsed_string: Option<String>,
+    ) -> Cow<'de, str> {
+        self.truncated_at.get_or_insert(self.event_start);
+        self.open_quote = None;
+        match parsed_string {
+            Some(mut s) => {
+                s.truncate(len);
+                Cow::Owned(s)
+            }
+            None => Cow::Borrowed(self.read.slice(content, mark.offset()).unwrap()),
+        }
+    }
+
+    /// Decodes the escape sequence starting at `mark`, whose backslash and
+    /// `escaped_char` have already been consumed.
+    fn escape(&mut self, escaped_char: u8, mark: Mark, out: &mut String) -> Result<()> {
+        let json5 = self.options.json5_syntax;
+        match escaped_char {
+            b'"' => out.push('"'),    // quotation mark
+            b'\\' => out.push('\\'),  // reverse solidus
+            b'/' => out.push('/'),    // solidus
+            b'b' => out.push('\x08'), // backspace
+            b'f' => out.push('\x0C'), // form feed
+            b'n' => out.push('\n'),   // line feed
+            b'r' => out.push('\r'),   // carriage return
+            b't' => out.push('\t'),   // tab
+            b'\'' if json5 => out.push('\''),
+            b'v' if json5 => out.push('\x0B'),
+            b'0' if json5 => {
+                if self.read.peek()?.is_some_and(|b| b.is_ascii_digit()) {
+                    return Err(Error::InvalidEscapeSequence(
+                        "\\0".to_string(),
+                        self.read.position(mark),
+                    ));
+                }
+                out.push('\0');
+            }
+            b'x' if json5 => out.push(char::from_u32(self.hex(2, mark)?).unwrap()),
+            // A backslash before a line break continues the string on the
+            // next line.
+            b'\n' if json5 => {}
+            b'\r' if json5 => {
+                if self.read.peek()? == Some(b'\n') {
+                    self.read.discard();
+                }
+            }
+            b'u' => {
+                let mut unit = self.hex(4, mark)?;
+                let mut unit_mark = mark;
+                loop {
+                    match unit {
+                        0xD800..=0xDBFF => {
+                            // Characters outside the BMP are escaped as a
+                            // surrogate pair: \uD83D\uDE00.
+                            if self.read.peek()? != Some(b'\\') {
+                                return self.lone_surrogate(unit_mark, out);
+                            }
+                            let next_mark = self.read.mark();
+                            self.read.discard();
+                            let Some(c) = self.read.next()? else {
+                                return Err(Error::InvalidEscapeSequence(
+                                    "\\".to_string(),
+                                    self.read.position(next_mark),
+                                ));
+                            };
+                            if c != b'u' {
+                                self.lone_surrogate(unit_mark, out)?;
+                                return self.escape(c, next_mark, out);
+                            }
+                            let low = self.hex(4, next_mark)?;
+                            if (0xDC00..=0xDFFF).contains(&low) {
+                                let c = 0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00);
+                                out.push(char::from_u32(c).unwrap());
+                                return Ok(());
+                            }
+                            self.lone_surrogate(unit_mark, out)?;
+                            unit = low;
+                            unit_mark = next_mark;
+                        }
+                        0xDC00..=0xDFFF => return self.lone_surrogate(unit_mark, out),
+                        _ => {
+                            out.push(char::from_u32(unit).unwrap());
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+            _ => {
+                let c = utf8_char(&mut self.read, escaped_char, mark)?;
+                if json5 && !c.is_ascii_digit() {
+                    // Any other character stands for itself, except for the
+                    // line separators that continue a string.
+                    if !matches!(c, '\u{2028}' | '\u{2029}') {
+                        out.push(c);
+                    }
+                    return Ok(());
+                }
+                return Err(Error::InvalidEscapeSequence(
+                    format!("\\{}", c),
+                    self.read.position(mark),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the hex digits of the `\u` or `\x` escape starting at `mark`.
+    fn hex(&mut self, len: usize, mark: Mark) -> Result<u32> {
+        let mut hex_val: u32 = 0;
+        for _ in 0..len {
+            let digit = self.read.next()?.and_then(|c| (c as char).to_digit(16));
+            match digit {
+                Some(digit) => hex_val = (hex_val << 4) | digit,
+                None => return Err(Error::InvalidUnicodeEscape(self.read.position(mark))),
+            }
+        }
+        Ok(hex_val)
+    }
+
+    fn lone_surrogate(&self, mark: Mark, out: &mut String) -> Result<()> {
+        match self.options.lone_surrogates {
+            LoneSurrogates::Reject => Err(Error::LoneSurrogate(self.read.position(mark))),
+            LoneSurrogates::Replace => {
+                out.push(char::REPLACEMENT_CHARACTER);
+                Ok(())
+            }
+        }
+    }
+
+    /// number = [ minus ] int [ frac ] [ exp ]
+    /// - int = zero / ( digit1-9 *DIGIT )
+    /// - frac = decimal-point 1*DIGIT
+    /// - exp = e [ minus / plus ] 1*DIGIT
+    ///
+    /// JSON5 also allows a leading plus sign, hexadecimal integers, a decimal
+    /// point without digits on one side, `Infinity` and `NaN`. Lexemes of such
+    /// numbers are kept in their JSON spelling, except for hexadecimal and
+    /// non-finite numbers, which are converted.
+    fn number(&mut self) -> Result<Event<'de>> {
+        let start = self.read.mark();
+        let json5 = self.options.json5_syntax;
+        let mut buf = String::new();
+        match self.peek_token()? {
+            Some(b'-') => {
+                buf.push('-');
+                self.read.discard();
+            }
+            Some(b'+') if json5 => self.read.discard(),
+            _ => {}
+        }
+        let negative = !buf.is_empty();
+        let leading_point = json5 && self.read.peek()? == Some(b'.');
+
+        match self.read.peek()? {
+            Some(b'0') => {
+                buf.push('0');
+                self.read.discard();
+                if json5 && matches!(self.read.peek()?, Some(b'x' | b'X')) {
+                    self.read.discard();
+                    return self.hex_number(negative, start);
+                }
+                if self.read.peek()?.is_some_and(|b| b.is_ascii_digit()) {
+                    return Err(Error::InvalidNumberFormat(
+                        "leading zeros are not allowed".to_string(),
+                        self.current_position(),
+                    ));
+                }
+            }
+            Some(b'1'..=b'9') => self.digits(&mut buf)?,
+            Some(b'.') if leading_point => buf.push('0'),
+            Some(b'I') if json5 => {
+                let n = if negative {
+                    f64::NEG_INFINITY
+                } else {
+                    f64::INFINITY
+                };
+                return self.keyword("Infinity", Event::Number(Number::Float(n)));
+            }
+            Some(b'N') if json5 => {
+                return self.keyword("NaN", Event::Number(Number::Float(f64::NAN)));
+            }
+            _ => return Err(self.expected_digit()),
+        }
+
+        if self.read.peek()? == Some(b'.') {
+            buf.push('.');
+            self.read.discard();
+            let has_digit = self.read.peek()?.is_some_and(|b| b.is_ascii_digit());
+            if json5 && !leading_point && !has_digit {
+                buf.push('0');
+            } else {
+                self.digits(&mut buf)?;
+            }
+        }
+
+        if let Some(e @ (b'e' | b'E')) = self.read.peek()? {
+            buf.push(e as char);
+            self.read.discard();
+            if let Some(sign @ (b'-' | b'+')) = self.read.peek()? {
+                buf.push(sign as char);
+                self.read.discard();
+            }
+            self.digits(&mut buf)?;
+        }
+
+        if self.options.preserve_number_lexemes {
+            return Ok(Event::Number(Number::Lexeme(buf)));
+        }
+        match Number::from_lexeme(&buf) {
+            Some(n) => Ok(Event::Number(n)),
+            None => Err(Error::InvalidNumberFormat(
+                "number out of range".to_string(),
+                self.read.position(start),
+            )),
+        }
+    }
+
+    /// hex = 0x 1*HEXDIG, read after the sign and `0x`.
+    fn hex_number(&mut self, negative: bool, start: Mark) -> Result<Event<'de>> {
+        let mut int = Some(0u64);
+        let mut float = 0.0;
+        if !self.read.peek()?.is_some_and(|b| b.is_ascii_hexdigit()) {
+            return Err(self.expected_digit());
+        }
+        while let Some(digit) = self.read.peek()?.and_then(|b| (b as char).to_digit(16)) {
+            self.read.discard();
+            int = int
+                .and_then(|n| n.checked_mul(16))
+                .and_then(|n| n.checked_add(digit.into()));
+            float = float * 16.0 + f64::from(digit);
+        }
+        let n = match (int, negative) {
+            (Some(n), false) => Number::PosInt(n),
+            (Some(0), true) => Number::Float(-0.0),
+            (Some(n), true) if n <= i64::MIN.unsigned_abs() => {
+                Number::NegInt((n as i64).wrapping_neg())
+            }
+            _ if float.is_infinite() => {
+                return Err(Error::InvalidNumberFormat(
+                    "number out of range".to_string(),
+                    self.read.position(start),
+                ));
+            }
+            (_, false) => Number::Float(float),
+            (_, true) => Number::Float(-float),
+        };
+        Ok(Event::Number(n))
+    }
+
+    /// Appends one or more digits to `buf`.
+    fn digits(&mut self, buf: &mut String) -> Result<()> {
+        if !self.read.peek()?.is_some_and(|b| b.is_ascii_digit()) {
+            return Err(self.expected_digit());
+        }
+        while let Some(b @ b'0'..=b'9') = self.read.peek()? {
+            buf.push(b as char);
+            self.read.discard();
+        }
+        Ok(())
+    }
+
+    fn expected_digit(&self) -> Error {
+        Error::InvalidNumberFormat("expected a digit".to_string(), self.current_position())
+    }
+}
+
+/// Reads the rest of the UTF-8 sequence starting with the already consumed
+/// byte `first`.
+fn utf8_char<'de, R: Read<'de>>(read: &mut R, first: u8, mark: Mark) -> Result<char> {
+    let width = utf8_width(first);
+    let mut seq = [first, 0, 0, 0];
+    for slot in seq.iter_mut().take(width).skip(1) {
+        match read.peek()? {
+            Some(b) if b & 0xC0 == 0x80 => {
+                read.discard();
+                *slot = b;
+            }
+            _ => break,
+        }
+    }
+    match std::str::from_utf8(&seq[..width]) {
+        Ok(s) if width > 0 => Ok(s.chars().next().unwrap()),
+        _ => Err(Error::InvalidUtf8(read.position(mark))),
+    }
+}
+
+impl<'de, R: Read<'de>> Iterator for Reader<'de, R> {
+    type Item = Result<Event<'de>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(json: &str) -> Result<Vec<Event<'_>>> {
+        Reader::new(json).collect()
+    }
+
+    #[test]
+    fn read_scalar() {
+        assert_eq!(events("true").unwrap(), vec![Event::Bool(true)]);
+        assert_eq!(events(" null ").unwrap(), vec![Event::Null]);
+        assert_eq!(
+            events("-1.5").unwrap(),
+            vec![Event::Number(Number::Float(-1.5))]
+        );
+    }
+
+    #[test]
+    fn read_nested() {
+        let json = r#"{"a": [1, {"b": null}], "c": {}, "d": []}"#;
+        assert_eq!(
+            events(json).unwrap(),
+            vec![
+                Event::StartObject,
+                Event::Key("a".into()),
+                Event::StartArray,
+                Event::Number(Number::PosInt(1)),
+                Event::StartObject,
+                Event::Key("b".into()),
+                Event::Null,
+                Event::EndObject,
+                Event::EndArray,
+                Event::Key("c".into()),
+                Event::StartObject,
+                Event::EndObject,
+                Event::Key("d".into()),
+                Event::StartArray,
+                Event::EndArray,
+                Event::EndObject,
+            ]
+        );
+    }
+
+    #[test]
+    fn read_depth() {
+        let mut reader = Reader::new("[[1]]");
+        assert_eq!(reader.depth(), 0);
+        reader.next_event().unwrap();
+        reader.next_event().unwrap();
+        assert_eq!(reader.depth(), 2);
+        reader.next_event().unwrap();
+        reader.next_event().unwrap();
+        assert_eq!(reader.depth(), 1);
+    }
+
+    #[test]
+    fn strings_borrow_from_input_without_escapes() {
+        let events = events(r#"{"plain": "text", "esc\n": "a\"b"}"#).unwrap();
+        assert!(matches!(&events[1], Event::Key(Cow::Borrowed("plain"))));
+        assert!(matches!(&events[2], Event::String(Cow::Borrowed("text"))));
+        assert!(matches!(&events[3], Event::Key(Cow::Owned(k)) if k == "esc\n"));
+        assert!(matches!(&events[4], Event::String(Cow::Owned(s)) if s == "a\"b"));
+    }
+
+    #[test]
+    fn read_from_io() {
+        let json = r#"["xあ", 2]"#;
+        let events: Vec<_> = Reader::from_reader(json.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                Event::StartArray,
+                Event::String("xあ".into()),
+                Event::Number(Number::PosInt(2)),
+                Event::EndArray,
+            ]
+        );
+    }
+
+    #[test]
+    fn error_ends_events() {
+        let mut reader = Reader::new("[1 2]");
+        assert_eq!(reader.next_event().unwrap(), Some(Event::StartArray));
+        assert_eq!(
+            reader.next_event().unwrap(),
+            Some(Event::Number(Number::PosInt(1)))
+        );
+        assert!(matches!(
+            reader.next_event(),
+            Err(Error::MissingExpectedChar(']', _))
+        ));
+        assert_eq!(reader.next_event().unwrap(), None);
+    }
+
+    #[test]
+    fn trailing_characters_after_document() {
+        let mut reader = Reader::new("{} x");
+        assert_eq!(reader.next_event().unwrap(), Some(Event::StartObject));
+        assert_eq!(reader.next_event().unwrap(), Some(Event::EndObject));
+        assert!(matches!(
+            reader.next_event(),
+            Err(Error::TrailingCharacters(ref p)) if p.offset == 3
+        ));
+    }
+
+    #[test]
+    fn unterminated_document() {
+        let err = events("[1,").unwrap_err();
+        assert!(matches!(err, Error::UnexpectedToken(ref p) if p.offset == 3));
+    }
+
+    #[test]
+    fn depth_limit() {
+        let options = ParseOptions::new().max_depth(2);
+        let events: Result<Vec<_>> = Reader::new("[{}]").with_options(options.clone()).collect();
+        assert!(events.is_ok());
+
+        let err = Reader::new("[{\"a\": []}]")
+            .with_options(options)
+            .collect::<Result<Vec<_>>>()
+            .unwrap_err();
+        assert!(matches!(err, Error::DepthLimitExceeded(ref p) if p.offset == 7));
+    }
+
+    #[test]
+    fn deep_nesting_without_recursion() {
+        let depth = 1_000_000;
+        let json = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let options = ParseOptions::new().max_depth(usize::MAX);
+        let mut reader = Reader::new(&json).with_options(options);
+        let mut deepest = 0;
+        while reader.next_event().unwrap().is_some() {
+            deepest = deepest.max(reader.depth());
+        }
+        assert_eq!(deepest, depth);
+    }
+
+    #[test]
+    fn jsonc_comments_and_trailing_commas() {
+        let json = r#"// settings
+{
+    /* editor */ "tabSize": 4, // spaces
+    "rulers": [80, 120,],
+    "files.exclude": {"**/.git": true,}, /* a
+    multi-line ** comment */
+}
+"#;
+        let events = Reader::new(json)
+            .with_options(ParseOptions::jsonc())
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                Event::StartObject,
+                Event::Key("tabSize".into()),
+                Event::Number(Number::PosInt(4)),
+                Event::Key("rulers".into()),
+                Event::StartArray,
+                Event::Number(Number::PosInt(80)),
+                Event::Number(Number::PosInt(120)),
+                Event::EndArray,
+                Event::Key("files.exclude".into()),
+                Event::StartObject,
+                Event::Key("**/.git".into()),
+                Event::Bool(true),
+                Event::EndObject,
+                Event::EndObject,
+            ]
+        );
+    }
+
+    #[test]
+    fn jsonc_errors() {
+        let read = |json| {
+            Reader::new(json)
+                .with_options(ParseOptions::jsonc())
+                .collect::<Result<Vec<_>>>()
+        };
+        assert!(matches!(read("[1 /* x"), Err(Error::UnterminatedComment(ref p)) if p.offset == 3));
+        assert!(matches!(read("[1 / 2]"), Err(Error::UnexpectedToken(ref p)) if p.offset == 3));
+        assert!(matches!(read("[1,,]"), Err(Error::UnexpectedToken(ref p)) if p.offset == 3));
+        assert!(matches!(read("[,]"), Err(Error::UnexpectedToken(_))));
+        assert!(matches!(
+            read("{,}"),
+            Err(Error::MissingExpectedChar('"', _))
+        ));
+    }
+
+    #[test]
+    fn comments_and_trailing_commas_rejected_by_default() {
+        let read = |json| Reader::new(json).collect::<Result<Vec<_>>>();
+        assert!(matches!(read("// x\n1"), Err(Error::UnexpectedToken(_))));
+        assert!(matches!(read("[1,]"), Err(Error::UnexpectedToken(_))));
+        assert!(matches!(
+            read(r#"{"a":1,}"#),
+            Err(Error::MissingExpectedChar('"', _))
+        ));
+    }
+
+    fn json5_events(json: &str) -> Result<Vec<Event<'_>>> {
+        Reader::new(json)
+            .with_options(ParseOptions::json5())
+            .collect()
+    }
+
+    #[test]
+    fn json5_keys_and_strings() {
+        let json =
+            "{unquoted: 'and you can quote me on that', $_ident1: \"\", l\u{e8}ve\u{A0}: 'x'}";
+        assert_eq!(
+            json5_events(json).unwrap(),
+            vec![
+                Event::StartObject,
+                Event::Key("unquoted".into()),
+                Event::String("and you can quote me on that".into()),
+                Event::Key("$_ident1".into()),
+                Event::String("".into()),
+                Event::Key("l\u{e8}ve".into()),
+                Event::String("x".into()),
+                Event::EndObject,
+            ]
+        );
+        assert!(matches!(
+            json5_events(r#"{"unquoted": 1}"#).unwrap()[1],
+            Event::Key(Cow::Borrowed("unquoted"))
+        ));
+        assert!(matches!(
+            json5_events("{unquoted: 1}").unwrap()[1],
+            Event::Key(Cow::Borrowed("unquoted"))
+        ));
+    }
+
+    #[test]
+    fn json5_string_escapes() {
+        let json = "['I can\\'t', \"say \\\"hi\\\"\", 'line 1 \\\nline 2', '\\x41\\v\\0\\q\\\r\n', '\t\"']";
+        assert_eq!(
+            json5_events(json).unwrap(),
+            vec![
+                Event::StartArray,
+                Event::String("I can't".into()),
+                Event::String("say \"hi\"".into()),
+                Event::String("line 1 line 2".into()),
+                Event::String("A\x0B\0q".into()),
+                Event::String("\t\"".into()),
+                Event::EndArray,
+            ]
+        );
+        assert!(matches!(
+            json5_events(r"'\01'"),
+            Err(Error::InvalidEscapeSequence(..))
+        ));
+        assert!(matches!(
+            json5_events(r"'\1'"),
+            Err(Error::InvalidEscapeSequence(..))
+        ));
+        assert!(matches!(
+            json5_events("'a\nb'"),
+            Err(Error::UnexpectedToken(_))
+        ));
+        assert!(matches!(
+            json5_events("'a\""),
+            Err(Error::UnterminatedString(_))
+        ));
+    }
+
+    #[test]
+    fn json5_numbers() {
+        let json = "[0xdecaf, -0XC0FFEE, +1, .5, 5., -.5e1, +Infinity, -Infinity, 0x0, -0x8000000000000000]";
+        let numbers: Vec<_> = json5_events(json)
+            .unwrap()
+            .into_iter()
+            .filter_map(|e| match e {
+                Event::Number(n) => Some(n),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            numbers,
+            vec![
+                Number::PosInt(0xdecaf),
+                Number::NegInt(-0xc0ffee),
+                Number::PosInt(1),
+                Number::Float(0.5),
+                Number::Float(5.0),
+                Number::Float(-5.0),
+                Number::Float(f64::INFINITY),
+                Number::Float(f64::NEG_INFINITY),
+                Number::PosInt(0),
+                Number::NegInt(i64::MIN),
+            ]
+        );
+        assert!(matches!(
+            json5_events("NaN").unwrap()[..],
+            [Event::Number(Number::Float(n))] if n.is_nan()
+        ));
+        assert_eq!(
+            json5_events("0x10000000000000000").unwrap(),
+            vec![Event::Number(Number::Float(18446744073709551616.0))]
+        );
+
+        let lexemes = ParseOptions::json5().preserve_number_lexemes(true);
+        let read = |json| {
+            Reader::new(json)
+                .with_options(lexemes.clone())
+                .collect::<Result<Vec<_>>>()
+                .unwrap()
+        };
+        assert_eq!(
+            read("[+.50, 1.]"),
+            vec![
+                Event::StartArray,
+                Event::Number(Number::Lexeme("0.50".into())),
+                Event::Number(Number::Lexeme("1.0".into())),
+                Event::EndArray,
+            ]
+        );
+        assert_eq!(read("0xff"), vec![Event::Number(Number::PosInt(255))]);
+
+        for json in [".", "+", "0x", "01", "-.e1", "Inf", "+NaNa", "0xg"] {
+            assert!(json5_events(json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn json5_whitespace() {
+        let json = "\u{FEFF}[1,\x0B\u{A0}\u{2028}2]\x0C";
+        assert_eq!(json5_events(json).unwrap().len(), 4);
+        assert!(matches!(
+            json5_events("[1, \u{e9}]"),
+            Err(Error::UnexpectedToken(ref p)) if p.offset == 4
+        ));
+        assert!(events("[1,\x0B2]").is_err());
+    }
+
+    #[test]
+    fn json5_syntax_rejected_by_default() {
+        for json in [
+            "{a: 1}",
+            "'a'",
+            "0x1",
+            "+1",
+            ".5",
+            "5.",
+            "Infinity",
+            "NaN",
+            "\"\\x41\"",
+        ] {
+            assert!(events(json).is_err(), "{json}");
+        }
+        assert!(events("\"a\tb\"").is_err());
+    }
+}
@@ -0,0 +1,409 @@
+//! Heuristic fixes for almost-JSON, such as the output of scripts and
+//! language models.
+
+/// A change [`repair`] made to its input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fix {
+    pub kind: FixKind,
+    /// Byte offset in the input where the fix was made.
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixKind {
+    /// Dropped a markdown code fence and any text outside it.
+    CodeFence,
+    /// Rewrote a single-quoted string with double quotes.
+    SingleQuotes,
+    /// Inserted a comma between two values.
+    MissingComma,
+    /// Quoted an object member name.
+    UnquotedKey,
+    /// Removed a comma before a closing bracket.
+    TrailingComma,
+    /// Replaced Python's `True`, `False` or `None`.
+    PythonLiteral,
+}
+
+impl std::fmt::Display for Fix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let what = match self.kind {
+            FixKind::CodeFence => "Removed code fence",
+            FixKind::SingleQuotes => "Replaced single quotes",
+            FixKind::MissingComma => "Inserted missing comma",
+            FixKind::UnquotedKey => "Quoted member name",
+            FixKind::TrailingComma => "Removed trailing comma",
+            FixKind::PythonLiteral => "Replaced Python literal",
+        };
+        write!(f, "{what} at byte {}", self.offset)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Object,
+    Array,
+}
+
+/// The last token written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Last {
+    Start,
+    Open,
+    /// A comma, with its index in the output and offset in the input.
+    Comma(usize, usize),
+    Colon,
+    Key,
+    Value,
+}
+
+/// Rewrites `input` into JSON where it can tell what was meant, and reports
+/// each [`Fix`] in input order.
+///
+/// Input that already parses is returned unchanged. The result is not
+/// guaranteed to be valid; parse it to find out.
+pub fn repair(input: &str) -> (String, Vec<Fix>) {
+    if crate::parse(input).is_ok() {
+        return (input.to_string(), Vec::new());
+    }
+    let mut fixes = Vec::new();
+    let (start, end) = match fenced(input) {
+        Some((fence, start, end)) => {
+            fixes.push(Fix {
+                kind: FixKind::CodeFence,
+                offset: fence,
+            });
+            (start, end)
+        }
+        None => (0, input.len()),
+    };
+    let mut repairer = Repairer {
+        input: &input[..end],
+        pos: start,
+        out: String::with_capacity(end - start),
+        fixes,
+        stack: Vec::new(),
+        last: Last::Start,
+        value_end: (0, 0),
+    };
+    repairer.run();
+    let Repairer { out, mut fixes, .. } = repairer;
+    fixes.sort_by_key(|fix| fix.offset);
+    (out, fixes)
+}
+
+/// Finds a ```` ``` ```` fence at the start of `input`, returning its offset
+/// and the span of the lines it encloses. The closing fence must start a
+/// line, so one inside a string does not end the block.
+fn fenced(input: &str) -> Option<(usize, usize, usize)> {
+    let fence = input.len() - input.trim_start().len();
+    if !input[fence..].starts_with("```") {
+        return None;
+    }
+    let start = input[fence..]
+        .find('\n')
+        .map_or(input.len(), |i| fence + i + 1);
+    let end = input[start..]
+        .match_indices("```")
+        .map(|(i, _)| start + i)
+        .find(|&i| input[..i].trim_end_matches([' ', '\t']).ends_with('\n'))
+        .unwrap_or(input.len());
+    Some((fence, start, end))
+}
+
+struct Repairer<'a> {
+    input: &'a str,
+    pos: usize,
+    out: String,
+    fixes: Vec<Fix>,
+    stack: Vec<Container>,
+    last: Last,
+    /// Where the last value ended, in the output and in the input.
+    value_end: (usize, usize),
+}
+
+impl Repairer<'_> {
+    fn run(&mut self) {
+        while let Some(c) = self.input[self.pos..].chars().next() {
+            match c {
+                '{' | '[' => {
+                    self.begin_value();
+                    self.copy(1);
+                    self.stack.push(match c {
+                        '{' => Container::Object,
+                        _ => Container::Array,
+                    });
+                    self.last = Last::Open;
+                }
+                '}' | ']' => {
+                    if let Last::Comma(index, offset) = self.last {
+                        self.out.remove(index);
+                        self.fix(FixKind::TrailingComma, offset);
+                    }
+                    self.stack.pop();
+                    self.copy(1);
+                    self.end_value();
+                }
+                ',' => {
+                    self.last = Last::Comma(self.out.len(), self.pos);
+                    self.copy(1);
+                }
+                ':' => {
+                    self.last = Last::Colon;
+                    self.copy(1);
+                }
+                '"' | '\'' => {
+                    let key = self.begin_value();
+                    if c == '"' {
+                        self.string();
+                    } else {
+                        self.fix(FixKind::SingleQuotes, self.pos);
+                        self.single_quoted_string();
+                    }
+                    if key {
+                        self.last = Last::Key;
+                    } else {
+                        self.end_value();
+                    }
+                }
+                '-' | '0'..='9' => {
+                    self.begin_value();
+                    let len = self.span(|c| c.is_ascii_digit() || "+-.eE".contains(c));
+                    self.copy(len);
+                    self.end_value();
+                }
+                c if c.is_ascii_alphabetic() || c == '_' || c == '$' => self.word(),
+                c => self.copy(c.len_utf8()),
+            }
+        }
+    }
+
+    /// Inserts a comma if a value follows another without one, and returns
+    /// whether the value is in the place of a member name.
+    fn begin_value(&mut self) -> bool {
+        if self.last == Last::Value && !self.stack.is_empty() {
+            let (index, offset) = self.value_end;
+            self.out.insert(index, ',');
+            self.fix(FixKind::MissingComma, offset);
+            self.last = Last::Comma(index, offset);
+        }
+        self.stack.last() == Some(&Container::Object)
+            && matches!(self.last, Last::Open | Last::Comma(..))
+    }
+
+    fn end_value(&mut self) {
+        self.last = Last::Value;
+        self.value_end = (self.out.len(), self.pos);
+    }
+
+    fn word(&mut self) {
+        let key = self.begin_value();
+        let len = self.span(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+        let word = &self.input[self.pos..self.pos + len];
+        if key {
+            self.fix(FixKind::UnquotedKey, self.pos);
+            self.out.push('"');
+            self.copy(len);
+            self.out.push('"');
+            self.last = Last::Key;
+            return;
+        }
+        let literal = match word {
+            "True" => Some("true"),
+            "False" => Some("false"),
+            "None" => Some("null"),
+            _ => None,
+        };
+        match literal {
+            Some(literal) => {
+                self.fix(FixKind::PythonLiteral, self.pos);
+                self.out.push_str(literal);
+                self.pos += len;
+            }
+            None => self.copy(len),
+        }
+        self.end_value();
+    }
+
+    fn string(&mut self) {
+        let mut escaped = false;
+        let len = 1 + self.input[self.pos + 1..]
+            .find(|c| {
+                let end = !escaped && c == '"';
+                escaped = !escaped && c == '\\';
+                end
+            })
+            .map_or(self.input.len() - self.pos - 1, |i| i + 1);
+        self.copy(len);
+    }
+
+    fn single_quoted_string(&mut self) {
+        self.pos += 1;
+        self.out.push('"');
+        let mut chars = self.input[self.pos..].chars();
+        while let Some(c) = chars.next() {
+            self.pos += c.len_utf8();
+            match c {
+                '\'' => break,
+                '"' => self.out.push_str("\\\""),
+                '\\' => match chars.next() {
+                    Some('\'') => {
+                        self.pos += 1;
+                        self.out.push('\'');
+                    }
+                    Some(c) => {
+                        self.pos += c.len_utf8();
+                        self.out.push('\\');
+                        self.out.push(c);
+                    }
+                    None => self.out.push('\\'),
+                },
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+
+    /// Length in bytes of the run of characters matching `f` at `pos`.
+    fn span(&self, f: impl Fn(char) -> bool) -> usize {
+        let rest = &self.input[self.pos..];
+        rest.find(|c| !f(c)).unwrap_or(rest.len())
+    }
+
+    fn copy(&mut self, len: usize) {
+        self.out.push_str(&self.input[self.pos..self.pos + len]);
+        self.pos += len;
+    }
+
+    fn fix(&mut self, kind: FixKind, offset: usize) {
+        self.fixes.push(Fix { kind, offset });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repaired(input: &str) -> (String, Vec<(FixKind, usize)>) {
+        let (out, fixes) = repair(input);
+        assert!(crate::parse(&out).is_ok(), "{out}");
+        (out, fixes.iter().map(|f| (f.kind, f.offset)).collect())
+    }
+
+    #[test]
+    fn valid_input_is_unchanged() {
+        let json = "{ \"a\" : [1, 2],\n \"b\": \"'c'\" }";
+        assert_eq!(repair(json), (json.to_string(), vec![]));
+    }
+
+    #[test]
+    fn single_quotes() {
+        let (out, fixes) = repaired(r#"{'a': 'it\'s "b"', "c": '\n'}"#);
+        assert_eq!(out, r#"{"a": "it's \"b\"", "c": "\n"}"#);
+        assert_eq!(
+            fixes,
+            [
+                (FixKind::SingleQuotes, 1),
+                (FixKind::SingleQuotes, 6),
+                (FixKind::SingleQuotes, 24),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_commas() {
+        let (out, fixes) = repaired("[1 2\n \"a\" {\"b\": [] \"c\": null} true]");
+        assert_eq!(out, "[1, 2,\n \"a\", {\"b\": [], \"c\": null}, true]");
+        let offsets: Vec<_> = fixes.iter().map(|(_, offset)| *offset).collect();
+        assert_eq!(offsets, [2, 4, 9, 18, 29]);
+        assert!(fixes.iter().all(|(kind, _)| *kind == FixKind::MissingComma));
+    }
+
+    #[test]
+    fn unquoted_keys() {
+        let (out, fixes) = repaired("{a: 1, $b_2: {c: []}}");
+        assert_eq!(out, r#"{"a": 1, "$b_2": {"c": []}}"#);
+        assert_eq!(
+            fixes,
+            [
+                (FixKind::UnquotedKey, 1),
+                (FixKind::UnquotedKey, 7),
+                (FixKind::UnquotedKey, 14),
+            ]
+        );
+    }
+
+    #[test]
+    fn trailing_commas() {
+        let (out, fixes) = repaired("{\"a\": [1, 2, ], \"b\": {},\n}");
+        assert_eq!(out, "{\"a\": [1, 2 ], \"b\": {}\n}");
+        assert_eq!(
+            fixes,
+            [(FixKind::TrailingComma, 11), (FixKind::TrailingComma, 23)]
+        );
+    }
+
+    #[test]
+    fn python_literals() {
+        let (out, fixes) = repaired("{'ok': True, 'err': None, 'retry': False}");
+        assert_eq!(out, r#"{"ok": true, "err": null, "retry": false}"#);
+        let kinds: Vec<_> = fixes.iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(
+            kinds
+                .iter()
+                .filter(|&&k| k == FixKind::PythonLiteral)
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn code_fences() {
+        let input = "\n```json\n{\"a\": [1, 2,]}\n```\nAnything else?";
+        let (out, fixes) = repaired(input);
+        assert_eq!(out, "{\"a\": [1, 2]}\n");
+        assert_eq!(
+            fixes,
+            [(FixKind::CodeFence, 1), (FixKind::TrailingComma, 20)]
+        );
+
+        let (out, _) = repaired("```\n[True]");
+        assert_eq!(out, "[true]");
+    }
+
+    #[test]
+    fn code_fences_in_strings() {
+        let (out, fixes) = repaired("{\"a\": \"```\", b: 1}");
+        assert_eq!(out, "{\"a\": \"```\", \"b\": 1}");
+        assert_eq!(fixes, [(FixKind::UnquotedKey, 13)]);
+
+        let (out, _) = repaired("```json\n{\"a\": \"x```\", b: 1}\n```");
+        assert_eq!(out, "{\"a\": \"x```\", \"b\": 1}\n");
+    }
+
+    #[test]
+    fn everything_at_once() {
+        let input = "```json\n{name: 'rj', tags: ['json' 'parser',], stable: False,}\n```";
+        let (out, fixes) = repaired(input);
+        assert_eq!(
+            crate::parse(&out).unwrap(),
+            crate::parse(r#"{"name": "rj", "tags": ["json", "parser"], "stable": false}"#).unwrap()
+        );
+        assert_eq!(fixes.len(), 11);
+    }
+
+    #[test]
+    fn unrepairable_input() {
+        let (out, _) = repair("{\"a\": undefined}");
+        assert!(crate::parse(&out).is_err());
+        let (out, fixes) = repair("'unterminated");
+        assert_eq!(out, "\"unterminated\"");
+        assert_eq!(
+            fixes,
+            [Fix {
+                kind: FixKind::SingleQuotes,
+                offset: 0
+            }]
+        );
+    }
+}
@@ -1,12 +1,14 @@
 use indexmap::IndexMap;
-use std::{ops::Index, str::FromStr};
+use std::{borrow::Cow, cmp::Ordering, ops::Index, str::FromStr};
 
-use crate::parse;
+use crate::Number;
+use crate::parse::{self, Build};
+use crate::reader::Event;
 
 #[derive(Debug, PartialEq)]
 pub enum Value {
     String(String),
-    Number(f64),
+    Number(Number),
     Boolean(bool),
     Null,
     Object(IndexMap<String, Value>),
@@ -37,6 +39,94 @@ impl Index<usize> for Value {
     }
 }
 
+impl Value {
+    /// Sorts the members of this and every nested object by key, comparing
+    /// code points.
+    pub fn sort_keys_recursive(&mut self) {
+        self.sort_keys_recursive_by(|a, b| a.cmp(b));
+    }
+
+    /// Sorts the members of this and every nested object by key with
+    /// `compare`, such as [`KeyOrder::compare`]. Members with equal keys keep
+    /// their order.
+    pub fn sort_keys_recursive_by(&mut self, mut compare: impl FnMut(&str, &str) -> Ordering) {
+        self.sort_keys_with(&mut compare);
+    }
+
+    fn sort_keys_with(&mut self, compare: &mut dyn FnMut(&str, &str) -> Ordering) {
+        match self {
+            Value::Object(obj) => {
+                obj.sort_by(|a, _, b, _| compare(a, b));
+                for v in obj.values_mut() {
+                    v.sort_keys_with(compare);
+                }
+            }
+            Value::Array(arr) => {
+                for v in arr {
+                    v.sort_keys_with(compare);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// An order for object members, for
+/// [`Value::sort_keys_recursive_by`] and
+/// [`FormatOptions::sort_keys`](crate::FormatOptions::sort_keys).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// By Unicode code point.
+    CodePoint,
+    /// By code point, except that runs of digits compare by their value, so
+    /// `item2` comes before `item10`.
+    Natural,
+    /// The listed keys first, in the order given, then the others by code
+    /// point. For manifests that start with `name` and `version`.
+    Priority(Vec<String>),
+}
+
+impl KeyOrder {
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            KeyOrder::CodePoint => a.cmp(b),
+            KeyOrder::Natural => natural(a, b),
+            KeyOrder::Priority(keys) => {
+                let rank = |key| keys.iter().position(|k| k == key).unwrap_or(keys.len());
+                rank(a).cmp(&rank(b)).then_with(|| a.cmp(b))
+            }
+        }
+    }
+}
+
+fn natural(a: &str, b: &str) -> Ordering {
+    let (mut x, mut y) = (a, b);
+    loop {
+        let (Some(c), Some(d)) = (x.chars().next(), y.chars().next()) else {
+            // Keys that only differ in leading zeros still need an order.
+            return x.len().cmp(&y.len()).then_with(|| a.cmp(b));
+        };
+        if c.is_ascii_digit() && d.is_ascii_digit() {
+            let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+            let (m, n) = (digits(x), digits(y));
+            let (p, q) = (
+                x[..m].trim_start_matches('0'),
+                y[..n].trim_start_matches('0'),
+            );
+            match p.len().cmp(&q.len()).then_with(|| p.cmp(q)) {
+                Ordering::Equal => (x, y) = (&x[m..], &y[n..]),
+                ord => return ord,
+            }
+        } else if c == d {
+            (x, y) = (&x[c.len_utf8()..], &y[d.len_utf8()..]);
+        } else {
+            return c.cmp(&d);
+        }
+    }
+}
+
+/// Parses JSON as [`parse`](fn@crate::parse) does, panicking on errors
+/// including nesting deeper than 128.
 impl From<&str> for Value {
     fn from(value: &str) -> Self {
         crate::parse(value).unwrap()
@@ -50,3 +140,182 @@ impl FromStr for Value {
         crate::parse(s)
     }
 }
+
+impl<'de> Build<'de> for Value {
+    type Key = String;
+
+    fn key(k: Cow<'de, str>) -> Self::Key {
+        k.into_owned()
+    }
+
+    fn scalar(event: Event<'de>) -> Self {
+        match event {
+            Event::String(s) => Value::String(s.into_owned()),
+            Event::Number(n) => Value::Number(n),
+            Event::Bool(b) => Value::Boolean(b),
+            Event::Null => Value::Null,
+            _ => unreachable!("not a scalar event: {event:?}"),
+        }
+    }
+
+    fn object(obj: IndexMap<Self::Key, Self>) -> Self {
+        Value::Object(obj)
+    }
+
+    fn array(arr: Vec<Self>) -> Self {
+        Value::Array(arr)
+    }
+
+    fn as_array_mut(&mut self) -> Option<&mut Vec<Self>> {
+        match self {
+            Value::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+}
+
+/// A document whose strings borrow from the input they were parsed from
+/// whenever they contain no escape sequences.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BorrowedValue<'a> {
+    String(Cow<'a, str>),
+    Number(Number),
+    Boolean(bool),
+    Null,
+    Object(IndexMap<Cow<'a, str>, BorrowedValue<'a>>),
+    Array(Vec<BorrowedValue<'a>>),
+}
+
+impl BorrowedValue<'_> {
+    /// Copies the borrowed strings into a regular [`Value`].
+    pub fn into_owned(self) -> Value {
+        match self {
+            BorrowedValue::String(s) => Value::String(s.into_owned()),
+            BorrowedValue::Number(n) => Value::Number(n),
+            BorrowedValue::Boolean(b) => Value::Boolean(b),
+            BorrowedValue::Null => Value::Null,
+            BorrowedValue::Object(obj) => Value::Object(
+                obj.into_iter()
+                    .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                    .collect(),
+            ),
+            BorrowedValue::Array(arr) => {
+                Value::Array(arr.into_iter().map(BorrowedValue::into_owned).collect())
+            }
+        }
+    }
+}
+
+impl<'a> Index<&str> for BorrowedValue<'a> {
+    type Output = BorrowedValue<'a>;
+
+    fn index(&self, index: &str) -> &Self::Output {
+        if let Self::Object(obj) = self {
+            &obj[index]
+        } else {
+            panic!("&str index only allowed for BorrowedValue::Object");
+        }
+    }
+}
+
+impl<'a> Index<usize> for BorrowedValue<'a> {
+    type Output = BorrowedValue<'a>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        if let Self::Array(arr) = self {
+            &arr[index]
+        } else {
+            panic!("integer index only allowed for BorrowedValue::Array");
+        }
+    }
+}
+
+impl<'de> Build<'de> for BorrowedValue<'de> {
+    type Key = Cow<'de, str>;
+
+    fn key(k: Cow<'de, str>) -> Self::Key {
+        k
+    }
+
+    fn scalar(event: Event<'de>) -> Self {
+        match event {
+            Event::String(s) => BorrowedValue::String(s),
+            Event::Number(n) => BorrowedValue::Number(n),
+            Event::Bool(b) => BorrowedValue::Boolean(b),
+            Event::Null => BorrowedValue::Null,
+            _ => unreachable!("not a scalar event: {event:?}"),
+        }
+    }
+
+    fn object(obj: IndexMap<Self::Key, Self>) -> Self {
+        BorrowedValue::Object(obj)
+    }
+
+    fn array(arr: Vec<Self>) -> Self {
+        BorrowedValue::Array(arr)
+    }
+
+    fn as_array_mut(&mut self) -> Option<&mut Vec<Self>> {
+        match self {
+            BorrowedValue::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(order: &KeyOrder, keys: &[&str]) -> Vec<String> {
+        let mut keys: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+        keys.sort_by(|a, b| order.compare(a, b));
+        keys
+    }
+
+    #[test]
+    fn code_point_order() {
+        let keys = ["b", "B", "a", "é", "a10", "a2", ""];
+        assert_eq!(
+            sorted(&KeyOrder::CodePoint, &keys),
+            ["", "B", "a", "a10", "a2", "b", "é"]
+        );
+    }
+
+    #[test]
+    fn natural_order() {
+        let keys = [
+            "item10", "item2", "item02", "item", "item1b", "item1a", "2", "10x",
+        ];
+        assert_eq!(
+            sorted(&KeyOrder::Natural, &keys),
+            [
+                "2", "10x", "item", "item1a", "item1b", "item02", "item2", "item10"
+            ]
+        );
+    }
+
+    #[test]
+    fn priority_order() {
+        let order = KeyOrder::Priority(vec!["name".into(), "version".into()]);
+        let keys = ["dependencies", "version", "authors", "name"];
+        assert_eq!(
+            sorted(&order, &keys),
+            ["name", "version", "authors", "dependencies"]
+        );
+    }
+
+    #[test]
+    fn sort_keys_recursive() {
+        let mut value = Value::from(r#"{"b": [{"d": 1, "c": 2}], "a": {"z": null, "y": {}}}"#);
+        value.sort_keys_recursive();
+        assert_eq!(
+            value.to_string(),
+            r#"{"a":{"y":{},"z":null},"b":[{"c":2,"d":1}]}"#
+        );
+
+        let mut value = Value::from(r#"{"x10": 1, "x9": 2}"#);
+        value.sort_keys_recursive_by(|a, b| KeyOrder::Natural.compare(a, b));
+        assert_eq!(value.to_string(), r#"{"x9":2,"x10":1}"#);
+    }
+}